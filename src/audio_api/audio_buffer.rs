@@ -30,6 +30,21 @@ impl AudioBuffer {
         })
     }
 
+    // Crea un buffer tomando posesión de datos ya separados por canal, sin copiarlos
+    pub fn from_channel_data(internal_data: Vec<Vec<f32>>, sample_rate: f32) -> Result<Self, Box<dyn Error>> {
+        let length = internal_data.first().map(Vec::len).unwrap_or(0);
+        if sample_rate <= 0.0 || length == 0 || internal_data.iter().any(|channel| channel.len() != length) {
+            return Err("NotSupportedError: Valores fuera de rango".into());
+        }
+
+        Ok(Self {
+            sample_rate,
+            length: length as u32,
+            number_of_channels: internal_data.len() as u32,
+            internal_data,
+        })
+    }
+
     // Retorna la tasa de muestreo
     pub fn sample_rate(&self) -> f32 {
         self.sample_rate
@@ -58,6 +73,14 @@ impl AudioBuffer {
         Ok(&mut self.internal_data[channel as usize])
     }
 
+    // Obtiene una referencia de solo lectura a los datos de un canal específico
+    pub fn channel_data(&self, channel: u32) -> Result<&[f32], Box<dyn Error>> {
+        if channel >= self.number_of_channels {
+            return Err("IndexSizeError: Número de canal fuera de rango".into());
+        }
+        Ok(&self.internal_data[channel as usize])
+    }
+
     // Copia datos de un canal a una array de destino
     pub fn copy_from_channel(
         &self,
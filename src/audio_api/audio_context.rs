@@ -1,8 +1,15 @@
+use std::path::Path;
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
-use rubato::{Resampler, SincFixedIn, SincInterpolationParameters, SincInterpolationType, WindowFunction};
+use super::audio_decoder::{self, DecodeError, PcmStream};
+use super::audio_destination_node::{AudioDestinationNode, RealtimeDestination};
+use super::audio_graph::AudioGraph;
+use super::resampler::{ResampleQuality, ResamplerCache, StreamResampler};
+use super::{AudioBuffer, BaseAudioContext, DeviceEvent, DeviceManager};
 
-use super::{audio_destination_node::AudioDestinationNode, AudioBuffer, AudioBufferOptions};
+// Frames por canal que se entregan al resampler en cada bloque al convertir un AudioBuffer
+const RESAMPLE_BLOCK_FRAMES: usize = 16384;
 
 pub struct AudioContext {
     pub sample_rate: f32,
@@ -10,114 +17,203 @@ pub struct AudioContext {
     pub output_latency: f32,
     render_quantum_size: u8,
     latency_hint: AudioContextLatencyCategory,
-    pub destination: AudioDestinationNode,
+    pub destination: RealtimeDestination,
+    device_manager: DeviceManager,
+    graph: Arc<Mutex<AudioGraph>>,
+    resample_quality: ResampleQuality,
+    resamplers: Mutex<ResamplerCache>,
 }
 
 impl AudioContext {
-    pub fn new(sample_rate: Option<f32>, latency_hint: Option<AudioContextLatencyCategory>) -> Self {
+    pub fn new(
+        sample_rate: Option<f32>,
+        latency_hint: Option<AudioContextLatencyCategory>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        Self::with_sink(sample_rate, latency_hint, DeviceManager::new(), None)
+    }
+
+    // Crea el contexto sobre un dispositivo de salida concreto (o el predeterminado si sink_id es None)
+    pub fn with_sink(
+        sample_rate: Option<f32>,
+        latency_hint: Option<AudioContextLatencyCategory>,
+        device_manager: DeviceManager,
+        sink_id: Option<&str>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
         let sample_rate = sample_rate.unwrap_or(44100.0); // Default to 44.1 kHz
         let latency_hint = latency_hint.unwrap_or(AudioContextLatencyCategory::Interactive);
 
+        // Create the render graph, the destination node and fetch output latency. The destination sets the graph
+        // channel count to the one of the device.
+        let graph = Arc::new(Mutex::new(AudioGraph::new(2)));
+        let destination = RealtimeDestination::new(&device_manager, sink_id, sample_rate, Arc::clone(&graph))?;
+        let output_latency = destination.output_latency();
+
+        // The device may not support the requested rate, in which case its own rate wins
+        let sample_rate = destination.sample_rate();
+
         // Calculate base latency in seconds
         let render_quantum_size = 128u8;
         let base_latency = (2 * render_quantum_size as u16) as f32 / sample_rate;
 
-        // Create the destination node and fetch output latency
-        let destination = AudioDestinationNode::new(sample_rate);
-        let output_latency = destination.output_latency();
-
-        Self {
+        Ok(Self {
             sample_rate,
             base_latency,
             output_latency,
             render_quantum_size,
             latency_hint,
             destination,
-        }
+            device_manager,
+            graph,
+            resample_quality: ResampleQuality::High,
+            resamplers: Mutex::new(ResamplerCache::default()),
+        })
     }
 
-    pub fn create_buffer(&self, number_of_channels: u32, length: u32, sample_rate: f32) -> Arc<Mutex<AudioBuffer>> {
-        let options = AudioBufferOptions {
-            number_of_channels,
-            length,
-            sample_rate,
-        };
-        Arc::new(Mutex::new(AudioBuffer::new(options).expect("Error creating buffer")))
+    pub fn device_manager(&self) -> &DeviceManager {
+        &self.device_manager
     }
 
-    pub fn create_buffer_from_data(&self, data: Vec<Vec<f32>>, sample_rate: f32) -> Arc<Mutex<AudioBuffer>> {
-        let number_of_channels = data.len() as u32;
-        let length = data[0].len() as u32;
-        let options = AudioBufferOptions {
-            number_of_channels,
-            length,
-            sample_rate,
-        };
-        let mut buffer = AudioBuffer::new(options).expect("Error creating buffer");
+    pub fn sink_id(&self) -> &str {
+        self.destination.device_id()
+    }
 
-        for (channel, channel_data) in data.iter().enumerate() {
-            buffer
-                .copy_to_channel(channel_data, channel as u32, 0)
-                .expect("Error copying data to buffer");
-        }
+    // Cambia el dispositivo de salida sin recrear el contexto. La tasa de muestreo no cambia: los nodos ya creados
+    // trabajan con ella, así que el destino rechaza los dispositivos que no la soportan.
+    pub fn switch_device(&mut self, device_id: &str) -> Result<(), Box<dyn std::error::Error>> {
+        self.destination.switch_device(&self.device_manager, device_id)?;
+
+        self.output_latency = self.destination.output_latency();
+        Ok(())
+    }
 
-        Arc::new(Mutex::new(buffer))
+    // Eventos pendientes del dispositivo de salida (p. ej. DeviceLost al desconectar un DAC USB)
+    pub fn poll_device_events(&self) -> Vec<DeviceEvent> {
+        self.destination.poll_events()
     }
 
+    // Decodifica un archivo en un AudioBuffer listo para reproducirse a la tasa de muestreo del contexto. Si el archivo
+    // tiene otra tasa, cada bloque decodificado pasa directamente por el resampler.
+    pub fn decode_audio_file<P: AsRef<Path>>(
+        &self,
+        path: P,
+        max_duration: Option<Duration>,
+    ) -> Result<Arc<Mutex<AudioBuffer>>, DecodeError> {
+        let stream = PcmStream::open(path.as_ref())?;
+        let input_sample_rate = stream.sample_rate();
+
+        let decoded = if input_sample_rate != self.sample_rate {
+            let mut resampler = self
+                .resamplers
+                .lock()
+                .unwrap()
+                .acquire(
+                    input_sample_rate,
+                    self.sample_rate,
+                    stream.number_of_channels(),
+                    self.resample_quality,
+                )
+                .map_err(|err| DecodeError::Resample(err.to_string()))?;
+            let result = audio_decoder::decode_stream(stream, max_duration, Some(&mut resampler));
+            self.resamplers.lock().unwrap().release(
+                input_sample_rate,
+                self.sample_rate,
+                self.resample_quality,
+                resampler,
+            );
+            result?
+        } else {
+            audio_decoder::decode_stream(stream, max_duration, None)?
+        };
+
+        let buffer = AudioBuffer::from_channel_data(decoded.channels, decoded.sample_rate)
+            .map_err(|err| DecodeError::Decoder(err.to_string()))?;
+        Ok(Arc::new(Mutex::new(buffer)))
+    }
+
+    // Resamplea el buffer a la tasa del contexto con la calidad por defecto del contexto
     pub fn resample_buffer(&self, buffer: &mut AudioBuffer) -> Result<AudioBuffer, Box<dyn std::error::Error>> {
+        self.resample_buffer_with_quality(buffer, self.resample_quality)
+    }
+
+    // Convierte el buffer por bloques, leyendo directamente de sus canales y acumulando solo la salida
+    pub fn resample_buffer_with_quality(
+        &self,
+        buffer: &AudioBuffer,
+        quality: ResampleQuality,
+    ) -> Result<AudioBuffer, Box<dyn std::error::Error>> {
         let channels = buffer.number_of_channels() as usize;
         let input_sample_rate = buffer.sample_rate();
-        let resample_ratio = self.sample_rate as f64 / input_sample_rate as f64;
-
-        // Configurar los parámetros de interpolación de Rubato
-        let params = SincInterpolationParameters {
-            sinc_len: 256,                                // Longitud del filtro sinc para una calidad alta
-            f_cutoff: 0.95,                               // Frecuencia de corte
-            interpolation: SincInterpolationType::Linear, // Interpolación lineal
-            oversampling_factor: 256,                     // Factor de sobremuestreo
-            window: WindowFunction::BlackmanHarris2,      // Ventana de Blackman-Harris
-        };
+        let length = buffer.length() as usize;
+
+        let input = (0..channels)
+            .map(|channel| buffer.channel_data(channel as u32))
+            .collect::<Result<Vec<&[f32]>, _>>()?;
 
-        // Crear el resampler de Rubato
-        let mut resampler = SincFixedIn::<f64>::new(
-            resample_ratio,           // Ratio de resampling calculado
-            2.0,                      // Máximo ratio de resampling relativo
-            params,                   // Parámetros de interpolación
-            buffer.length() as usize, // Tamaño de chunk (número de frames en la entrada)
-            channels,                 // Número de canales
-        )?;
-
-        // Organizar los datos del buffer en una estructura compatible con Rubato
-        let input_data: Vec<Vec<f64>> = (0..channels)
-            .map(|channel| {
-                buffer
-                    .get_channel_data(channel as u32)
-                    .unwrap()
-                    .iter()
-                    .map(|&s| s as f64)
-                    .collect()
-            })
+        // Reutilizar un resampler de la caché y devolverlo al terminar, incluso si falla
+        let mut resampler =
+            self.resamplers
+                .lock()
+                .unwrap()
+                .acquire(input_sample_rate, self.sample_rate, channels, quality)?;
+        let mut output: Vec<Vec<f32>> = (0..channels)
+            .map(|_| Vec::with_capacity(resampler.output_frames(length)))
             .collect();
+        let result = Self::resample_channels(&mut resampler, &input, &mut output);
+        self.resamplers
+            .lock()
+            .unwrap()
+            .release(input_sample_rate, self.sample_rate, quality, resampler);
+        result?;
 
-        // Ejecutar el resampling
-        let output_data = resampler.process(&input_data, None)?;
+        if output[0].is_empty() {
+            return Err("NotSupportedError: El buffer resampleado está vacío".into());
+        }
+        AudioBuffer::from_channel_data(output, self.sample_rate)
+    }
 
-        // Crear un nuevo buffer para almacenar los datos resampleados
-        let new_length = ((buffer.length() as f64) * resample_ratio).round() as u32;
+    fn resample_channels(
+        resampler: &mut StreamResampler,
+        input: &[&[f32]],
+        output: &mut [Vec<f32>],
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let length = input[0].len();
+        let mut start = 0;
+        while start < length {
+            let end = (start + RESAMPLE_BLOCK_FRAMES).min(length);
+            let block: Vec<&[f32]> = input.iter().map(|channel| &channel[start..end]).collect();
+            for (channel, data) in output.iter_mut().zip(resampler.resample_planar(&block)?) {
+                channel.extend(data);
+            }
+            start = end;
+        }
+        for (channel, data) in output.iter_mut().zip(resampler.flush_planar()?) {
+            channel.extend(data);
+        }
+        Ok(())
+    }
 
-        let mut resampled_buffer = AudioBuffer::new(AudioBufferOptions {
-            number_of_channels: channels as u32,
-            length: new_length,
-            sample_rate: self.sample_rate,
-        })?;
+    pub fn resample_quality(&self) -> ResampleQuality {
+        self.resample_quality
+    }
 
-        // Copiar los datos resampleados al nuevo buffer
-        for (channel, data) in output_data.iter().enumerate() {
-            let data_f32: Vec<f32> = data.iter().map(|&s| s as f32).collect();
-            resampled_buffer.copy_to_channel(&data_f32, channel as u32, 0)?;
-        }
+    pub fn set_resample_quality(&mut self, quality: ResampleQuality) {
+        self.resample_quality = quality;
+    }
 
-        Ok(resampled_buffer)
+    // Aciertos y fallos de la caché de resamplers
+    pub fn resampler_cache_stats(&self) -> (u64, u64) {
+        let cache = self.resamplers.lock().unwrap();
+        (cache.hits(), cache.misses())
+    }
+}
+
+impl BaseAudioContext for AudioContext {
+    fn sample_rate(&self) -> f32 {
+        self.sample_rate
+    }
+
+    fn graph(&self) -> &Arc<Mutex<AudioGraph>> {
+        &self.graph
     }
 }
 
@@ -134,3 +230,57 @@ pub enum AudioContextState {
     Running,
     Closed,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio_api::mock_host::MockHost;
+    use crate::audio_api::test_wav::write_sine_wav;
+    use crate::audio_api::OutputConfig;
+
+    fn test_context() -> AudioContext {
+        let config = OutputConfig {
+            channels: 2,
+            sample_rate: 44100,
+        };
+        let host = MockHost::new().with_device("default", true, config);
+        AudioContext::with_sink(None, None, DeviceManager::with_host(Arc::new(host)), None).unwrap()
+    }
+
+    #[test]
+    fn resampler_cache_reuses_resamplers_per_conversion() {
+        let context = test_context();
+        let buffer = AudioBuffer::from_channel_data(vec![vec![0.25; 4800]; 2], 48000.0).unwrap();
+
+        let first = context
+            .resample_buffer_with_quality(&buffer, ResampleQuality::Fast)
+            .unwrap();
+        let second = context
+            .resample_buffer_with_quality(&buffer, ResampleQuality::Fast)
+            .unwrap();
+        assert_eq!(context.resampler_cache_stats(), (1, 1));
+        assert_eq!(first.length(), 4410);
+        assert_eq!(second.length(), 4410);
+
+        // Otra calidad es otra entrada de la caché
+        context
+            .resample_buffer_with_quality(&buffer, ResampleQuality::High)
+            .unwrap();
+        assert_eq!(context.resampler_cache_stats(), (1, 2));
+    }
+
+    #[test]
+    fn decode_audio_file_resamples_to_context_rate() {
+        let context = test_context();
+        let path = write_sine_wav("context-22k", 22050, 2, 11025, 440.0);
+
+        let buffer = context.decode_audio_file(&path, None).unwrap();
+        let buffer = buffer.lock().unwrap();
+
+        assert_eq!(buffer.sample_rate(), 44100.0);
+        assert_eq!(buffer.number_of_channels(), 2);
+        assert_eq!(buffer.length(), 22050);
+        assert_eq!(context.resampler_cache_stats(), (0, 1));
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,281 @@
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+use std::time::Duration;
+
+use hound::{SampleFormat, WavReader};
+
+use super::resampler::StreamResampler;
+
+#[derive(Debug)]
+pub enum DecodeError {
+    Io(std::io::Error),
+    UnsupportedFormat(String),
+    Decoder(String),
+    Empty,
+    Resample(String),
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::Io(err) => write!(f, "Error de E/S al leer el archivo: {}", err),
+            DecodeError::UnsupportedFormat(format) => write!(f, "NotSupportedError: Formato no soportado: {}", format),
+            DecodeError::Decoder(message) => write!(f, "EncodingError: {}", message),
+            DecodeError::Empty => write!(f, "EncodingError: El archivo no contiene audio"),
+            DecodeError::Resample(message) => write!(f, "Error al resamplear el audio decodificado: {}", message),
+        }
+    }
+}
+
+impl Error for DecodeError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            DecodeError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<hound::Error> for DecodeError {
+    fn from(err: hound::Error) -> Self {
+        match err {
+            hound::Error::IoError(err) => DecodeError::Io(err),
+            hound::Error::Unsupported => DecodeError::UnsupportedFormat("WAV".to_string()),
+            // Sin cabecera RIFF/WAVE el archivo es de otro contenedor (FLAC, MP3...), no un WAV dañado
+            hound::Error::FormatError(message @ ("no RIFF tag found" | "no WAVE tag found")) => {
+                DecodeError::UnsupportedFormat(format!("solo se admiten archivos WAV ({})", message))
+            }
+            other => DecodeError::Decoder(other.to_string()),
+        }
+    }
+}
+
+// Audio decodificado, ya separado por canales y a la tasa de muestreo original del archivo
+pub struct DecodedAudio {
+    pub sample_rate: f32,
+    pub channels: Vec<Vec<f32>>,
+}
+
+// Decodificador incremental de archivos WAV: entrega el audio por bloques separados por canal, sin cargar el archivo
+// entero. Solo entiende WAV (PCM entero o float de 32 bits); no es una capa de detección de formatos, y cualquier otro
+// contenedor se rechaza con DecodeError::UnsupportedFormat.
+pub struct PcmStream {
+    reader: WavReader<BufReader<File>>,
+    sample_format: SampleFormat,
+    bits_per_sample: u16,
+    sample_rate: f32,
+    number_of_channels: usize,
+}
+
+impl PcmStream {
+    pub fn open(path: &Path) -> Result<Self, DecodeError> {
+        let reader = WavReader::open(path)?;
+        let spec = reader.spec();
+
+        if spec.channels == 0 || spec.sample_rate == 0 {
+            return Err(DecodeError::UnsupportedFormat(format!(
+                "{} canales a {}Hz",
+                spec.channels, spec.sample_rate
+            )));
+        }
+        match (spec.sample_format, spec.bits_per_sample) {
+            (SampleFormat::Float, 32) | (SampleFormat::Int, 1..=32) => {}
+            (format, bits) => {
+                return Err(DecodeError::UnsupportedFormat(format!("{:?} de {} bits", format, bits)));
+            }
+        }
+
+        Ok(Self {
+            reader,
+            sample_format: spec.sample_format,
+            bits_per_sample: spec.bits_per_sample,
+            sample_rate: spec.sample_rate as f32,
+            number_of_channels: spec.channels as usize,
+        })
+    }
+
+    pub fn sample_rate(&self) -> f32 {
+        self.sample_rate
+    }
+
+    pub fn number_of_channels(&self) -> usize {
+        self.number_of_channels
+    }
+
+    // Duración total en frames según la cabecera
+    pub fn total_frames(&self) -> usize {
+        self.reader.duration() as usize
+    }
+
+    // Reposiciona el decodificador en el frame indicado
+    pub fn seek(&mut self, frame: usize) -> Result<(), DecodeError> {
+        let frame = frame.min(self.total_frames()) as u32;
+        self.reader.seek(frame).map_err(DecodeError::Io)
+    }
+
+    // Lee hasta `frames` frames. Devuelve None cuando ya no queda audio.
+    pub fn read_chunk(&mut self, frames: usize) -> Result<Option<Vec<Vec<f32>>>, DecodeError> {
+        let mut channels: Vec<Vec<f32>> = (0..self.number_of_channels)
+            .map(|_| Vec::with_capacity(frames))
+            .collect();
+
+        match self.sample_format {
+            SampleFormat::Float => read_samples(self.reader.samples::<f32>(), &mut channels, frames, |s: f32| s)?,
+            SampleFormat::Int => {
+                let scale = 1.0 / (1u64 << (self.bits_per_sample - 1)) as f32;
+                read_samples(self.reader.samples::<i32>(), &mut channels, frames, |s: i32| {
+                    s as f32 * scale
+                })?
+            }
+        }
+
+        // Un archivo truncado puede terminar a mitad de un frame
+        let length = channels.iter().map(Vec::len).min().unwrap_or(0);
+        if length == 0 {
+            return Ok(None);
+        }
+        for channel in channels.iter_mut() {
+            channel.truncate(length);
+        }
+        Ok(Some(channels))
+    }
+}
+
+// Frames leídos por bloque al decodificar un archivo completo
+const DECODE_CHUNK_FRAMES: usize = 16384;
+
+// Decodifica un archivo por bloques directamente en los canales de salida, sin un buffer intercalado intermedio.
+// Con max_duration solo se decodifica el comienzo del archivo (útil para previsualizaciones). Si se pasa un resampler,
+// cada bloque se convierte nada más leerse y solo se acumula la salida ya resampleada.
+pub fn decode_stream(
+    mut stream: PcmStream,
+    max_duration: Option<Duration>,
+    mut resampler: Option<&mut StreamResampler>,
+) -> Result<DecodedAudio, DecodeError> {
+    let max_frames = max_duration
+        .map(|duration| (duration.as_secs_f64() * stream.sample_rate() as f64).ceil() as usize)
+        .unwrap_or(usize::MAX);
+    let frames = stream.total_frames().min(max_frames);
+
+    let (sample_rate, capacity) = match resampler.as_deref() {
+        Some(resampler) => (resampler.output_sample_rate(), resampler.output_frames(frames)),
+        None => (stream.sample_rate(), frames),
+    };
+    let mut channels: Vec<Vec<f32>> = (0..stream.number_of_channels())
+        .map(|_| Vec::with_capacity(capacity))
+        .collect();
+
+    let mut remaining = frames;
+    while remaining > 0 {
+        let Some(chunk) = stream.read_chunk(remaining.min(DECODE_CHUNK_FRAMES))? else {
+            break;
+        };
+        remaining -= chunk[0].len();
+
+        match resampler.as_deref_mut() {
+            Some(resampler) => {
+                let resampled = resampler.resample_planar(&chunk).map_err(resample_error)?;
+                append_channels(&mut channels, resampled);
+            }
+            None => append_channels(&mut channels, chunk),
+        }
+    }
+    if let Some(resampler) = resampler {
+        append_channels(&mut channels, resampler.flush_planar().map_err(resample_error)?);
+    }
+
+    if channels[0].is_empty() {
+        return Err(DecodeError::Empty);
+    }
+
+    Ok(DecodedAudio { sample_rate, channels })
+}
+
+fn append_channels(channels: &mut [Vec<f32>], data: Vec<Vec<f32>>) {
+    for (channel, data) in channels.iter_mut().zip(data) {
+        channel.extend(data);
+    }
+}
+
+fn resample_error(err: Box<dyn Error>) -> DecodeError {
+    DecodeError::Resample(err.to_string())
+}
+
+fn read_samples<S, I, F>(samples: I, channels: &mut [Vec<f32>], frames: usize, convert: F) -> Result<(), DecodeError>
+where
+    I: Iterator<Item = Result<S, hound::Error>>,
+    F: Fn(S) -> f32,
+{
+    let number_of_channels = channels.len();
+
+    for (index, sample) in samples.take(frames.saturating_mul(number_of_channels)).enumerate() {
+        channels[index % number_of_channels].push(convert(sample?));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio_api::test_wav::write_sine_wav;
+    use crate::audio_api::ResampleQuality;
+
+    #[test]
+    fn decodes_every_frame_and_channel() {
+        let path = write_sine_wav("decode-stereo", 44100, 2, 30000, 440.0);
+
+        let decoded = decode_stream(PcmStream::open(&path).unwrap(), None, None).unwrap();
+
+        assert_eq!(decoded.sample_rate, 44100.0);
+        assert_eq!(decoded.channels.len(), 2);
+        assert!(decoded.channels.iter().all(|channel| channel.len() == 30000));
+        let peak = decoded.channels[0]
+            .iter()
+            .fold(0.0f32, |peak, sample| peak.max(sample.abs()));
+        assert!((peak - 0.5).abs() < 1e-2);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn max_duration_limits_decoded_frames() {
+        let path = write_sine_wav("decode-preview", 44100, 1, 44100, 440.0);
+
+        let decoded = decode_stream(PcmStream::open(&path).unwrap(), Some(Duration::from_millis(250)), None).unwrap();
+
+        assert_eq!(decoded.channels[0].len(), 11025);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn resamples_while_decoding() {
+        let path = write_sine_wav("decode-22k", 22050, 2, 22050, 440.0);
+        let mut resampler = StreamResampler::new(22050.0, 44100.0, 2, ResampleQuality::Balanced).unwrap();
+
+        let decoded = decode_stream(PcmStream::open(&path).unwrap(), None, Some(&mut resampler)).unwrap();
+
+        // 22,05 kHz -> 44,1 kHz: el doble de frames
+        assert_eq!(decoded.sample_rate, 44100.0);
+        assert_eq!(decoded.channels.len(), 2);
+        assert!(decoded.channels.iter().all(|channel| channel.len() == 44100));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn other_containers_are_unsupported_formats() {
+        for (name, header) in [("flac", &b"fLaC"[..]), ("mp3", &b"ID3\x04"[..])] {
+            let path = std::env::temp_dir().join(format!("cismu-{}-decode-{}.bin", std::process::id(), name));
+            let mut contents = header.to_vec();
+            contents.resize(64, 0);
+            std::fs::write(&path, contents).unwrap();
+
+            let err = PcmStream::open(&path).err().unwrap();
+
+            assert!(matches!(err, DecodeError::UnsupportedFormat(_)), "{}: {}", name, err);
+            assert!(err.to_string().starts_with("NotSupportedError"));
+            std::fs::remove_file(path).unwrap();
+        }
+    }
+}
@@ -1,111 +1,199 @@
+use std::error::Error;
+use std::sync::mpsc::{self, Receiver, Sender};
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use super::audio_graph::{AudioGraph, RENDER_QUANTUM_SIZE};
+use super::device_manager::{
+    AudioHost, DeviceEvent, DeviceManager, OutputCallback, OutputConfig, OutputDeviceInfo, OutputStream, StreamFailure,
+};
+use super::nodes::remix;
+use super::AudioBuffer;
+
+// Número de callbacks usados para estimar la latencia de salida
+const LATENCY_PROBE_CALLBACKS: usize = 20;
+// Tiempo máximo de espera para la medición de latencia antes de darla por desconocida
+const LATENCY_PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+
+// Comportamiento común a los destinos en tiempo real y offline
+pub trait AudioDestinationNode {
+    fn sample_rate(&self) -> f32;
+    fn channel_count(&self) -> u16;
+}
 
-use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
-use cpal::{SampleFormat, Stream, SupportedStreamConfig};
+// Destino en tiempo real: alimenta un stream de salida del dispositivo
+pub struct RealtimeDestination {
+    device_id: String,
+    config: OutputConfig,
+    output_latency: f32,
+    stream: Box<dyn OutputStream>,
+    graph: Arc<Mutex<AudioGraph>>,
+    event_sender: Sender<DeviceEvent>,
+    events: Receiver<DeviceEvent>,
+}
 
-pub struct AudioDestinationNode {
-    supported_config: SupportedStreamConfig,
-    sample_format: SampleFormat,
-    sample_rate: f32,
-    channels: u16,
-    buffer_size: u32,
+// Dispositivo con su stream ya en marcha, pendiente de pasar a ser la salida del destino
+struct OpenedDevice {
+    device_id: String,
+    config: OutputConfig,
     output_latency: f32,
-    stream: Option<Stream>,
+    stream: Box<dyn OutputStream>,
 }
 
-impl AudioDestinationNode {
-    pub fn new(sample_rate: f32) -> Self {
-        let host = cpal::default_host();
-        let device = host
-            .default_output_device()
-            .expect("No se encontró un dispositivo de salida");
-
-        let supported_config = device.default_output_config().unwrap();
-        let sample_format = supported_config.sample_format();
-        let sample_rate = supported_config.sample_rate().0 as f32;
-        let channels = supported_config.channels();
-
-        let buffer_size = match supported_config.buffer_size() {
-            cpal::SupportedBufferSize::Range { min, .. } if *min > 0 => *min,
-            _ => 128,
+impl RealtimeDestination {
+    pub fn new(
+        device_manager: &DeviceManager,
+        device_id: Option<&str>,
+        sample_rate: f32,
+        graph: Arc<Mutex<AudioGraph>>,
+    ) -> Result<Self, Box<dyn Error>> {
+        let host = device_manager.host();
+        let (event_sender, events) = mpsc::channel();
+
+        let open_default = || -> Result<OpenedDevice, Box<dyn Error>> {
+            let device = device_manager.default_output_device()?;
+            Self::open_device(host.as_ref(), &device, sample_rate as u32, &graph, &event_sender)
         };
 
-        let mut destination = Self {
-            supported_config,
-            sample_format,
-            sample_rate,
-            channels,
-            buffer_size,
-            output_latency: 0.0,
-            stream: None,
+        // Si el dispositivo pedido no existe o no se puede abrir se recurre al dispositivo por defecto
+        let requested = device_id.map(|id| {
+            device_manager
+                .find_output_device(id)
+                .and_then(|device| Self::open_device(host.as_ref(), &device, sample_rate as u32, &graph, &event_sender))
+        });
+        let opened = match requested {
+            Some(Ok(opened)) => opened,
+            Some(Err(err)) => {
+                eprintln!(
+                    "No se pudo abrir el dispositivo solicitado, usando el predeterminado: {}",
+                    err
+                );
+                open_default()?
+            }
+            None => open_default()?,
         };
 
-        destination.calculate_output_latency(&device);
-        destination
+        // El grafo trabaja con los canales del dispositivo
+        graph.lock().unwrap().set_channel_count(opened.config.channels as usize);
+
+        Ok(Self {
+            device_id: opened.device_id,
+            config: opened.config,
+            output_latency: opened.output_latency,
+            stream: opened.stream,
+            graph,
+            event_sender,
+            events,
+        })
     }
 
-    fn build_stream<T>(&mut self, device: &cpal::Device) -> Stream
-    where
-        T: cpal::Sample + From<f32> + cpal::SizedSample,
-    {
-        let config = cpal::StreamConfig {
-            channels: self.channels,
-            sample_rate: cpal::SampleRate(self.sample_rate as u32),
-            buffer_size: cpal::BufferSize::Default,
-        };
-
-        let stream = device
-            .build_output_stream(
-                &config,
-                |data: &mut [T], _| {}, // Stream output callback
-                |err| eprintln!("Error en el stream de salida: {:?}", err),
-                None,
-            )
-            .expect("No se pudo crear el stream de salida");
-
-        stream.play().expect("No se pudo reproducir el stream");
-        stream
+    // Usa la tasa de muestreo pedida si el dispositivo la soporta; si no, la de su configuración por defecto
+    fn select_config(
+        host: &dyn AudioHost,
+        device: &OutputDeviceInfo,
+        sample_rate: u32,
+    ) -> Result<OutputConfig, Box<dyn Error>> {
+        let mut config = host.default_output_config(&device.id)?;
+        if device.supports_sample_rate(sample_rate) {
+            config.sample_rate = sample_rate;
+        }
+        Ok(config)
     }
 
-    fn calculate_output_latency(&mut self, device: &cpal::Device) {
-        let config = cpal::StreamConfig {
-            channels: self.channels,
-            sample_rate: cpal::SampleRate(self.sample_rate as u32),
-            buffer_size: cpal::BufferSize::Default,
-        };
-
-        let (sender, receiver) = std::sync::mpsc::sync_channel(1);
-        let latency_samples = Arc::new(Mutex::new(Vec::new()));
-        let latency_samples_clone = Arc::clone(&latency_samples);
-
-        let stream = device
-            .build_output_stream(
-                &config,
-                move |_data: &mut [f32], output_callback_info| {
-                    let latency_duration = output_callback_info
-                        .timestamp()
-                        .playback
-                        .duration_since(&output_callback_info.timestamp().callback);
-
-                    if let Some(duration) = latency_duration {
-                        let latency_millis = duration.as_millis() as f32;
-                        let mut latencies = latency_samples_clone.lock().unwrap();
-                        latencies.push(latency_millis);
-
-                        if latencies.len() >= 20 {
-                            sender.send(()).unwrap();
-                        }
+    // Mide la latencia y arranca el stream del dispositivo sin tocar el estado del destino
+    fn open_device(
+        host: &dyn AudioHost,
+        device: &OutputDeviceInfo,
+        sample_rate: u32,
+        graph: &Arc<Mutex<AudioGraph>>,
+        event_sender: &Sender<DeviceEvent>,
+    ) -> Result<OpenedDevice, Box<dyn Error>> {
+        let config = Self::select_config(host, device, sample_rate)?;
+        let output_latency = Self::calculate_output_latency(host, &device.id, config);
+
+        let data_callback = Self::render_callback(Arc::clone(graph), config);
+
+        let device_id = device.id.clone();
+        let event_sender = event_sender.clone();
+        let error_callback = Box::new(move |failure: StreamFailure| match failure {
+            StreamFailure::DeviceNotAvailable => {
+                let _ = event_sender.send(DeviceEvent::DeviceLost { id: device_id.clone() });
+            }
+            StreamFailure::Other(err) => eprintln!("Error en el stream de salida: {}", err),
+        });
+
+        let stream = host.build_output_stream(&device.id, config, data_callback, error_callback)?;
+        stream.play()?;
+
+        Ok(OpenedDevice {
+            device_id: device.id.clone(),
+            config,
+            output_latency,
+            stream,
+        })
+    }
+    // Renderiza el grafo en bloques de RENDER_QUANTUM_SIZE y reparte sus canales entre los del dispositivo
+    fn render_callback(graph: Arc<Mutex<AudioGraph>>, config: OutputConfig) -> OutputCallback {
+        let channels = config.channels.max(1) as usize;
+        let sample_rate = config.sample_rate as f32;
+        let mut pending: Vec<Vec<f32>> = Vec::new();
+        let mut position = 0;
+
+        Box::new(move |data: &mut [f32], _: Option<Duration>| {
+            for frame in data.chunks_mut(channels) {
+                if pending.is_empty() || position == pending[0].len() {
+                    let mut graph = graph.lock().unwrap();
+                    let block = graph.render(RENDER_QUANTUM_SIZE, sample_rate);
+                    pending.resize_with(block.len(), Vec::new);
+                    for (pending, block) in pending.iter_mut().zip(block) {
+                        pending.clear();
+                        pending.extend_from_slice(block);
                     }
-                },
-                |err| eprintln!("Error en el stream de salida temporal: {:?}", err),
-                None,
-            )
-            .expect("No se pudo crear el stream de salida temporal");
+                    position = 0;
+                }
+
+                for (channel, sample) in frame.iter_mut().enumerate() {
+                    *sample = remix(pending.len(), channels, channel, |input| pending[input][position]);
+                }
+                position += 1;
+            }
+        })
+    }
 
-        stream.play().expect("No se pudo iniciar el stream temporal");
-        receiver.recv().expect("Error al recibir señal de finalización");
+    // Latencia de salida en segundos, o 0 si no se puede medir
+    fn calculate_output_latency(host: &dyn AudioHost, device_id: &str, config: OutputConfig) -> f32 {
+        let (sender, receiver) = mpsc::sync_channel(1);
+        let mut latency_samples = Vec::with_capacity(LATENCY_PROBE_CALLBACKS);
 
-        self.output_latency = Self::calculate_median_latency(&latency_samples.lock().unwrap()) / 1000.0;
+        let data_callback: OutputCallback = Box::new(move |data: &mut [f32], latency: Option<Duration>| {
+            data.fill(0.0);
+
+            if let Some(duration) = latency {
+                if latency_samples.len() < LATENCY_PROBE_CALLBACKS {
+                    latency_samples.push(duration.as_secs_f32() * 1000.0);
+                    if latency_samples.len() == LATENCY_PROBE_CALLBACKS {
+                        let _ = sender.try_send(Self::calculate_median_latency(&latency_samples));
+                    }
+                }
+            }
+        });
+        let error_callback =
+            Box::new(|failure: StreamFailure| eprintln!("Error en el stream de salida temporal: {:?}", failure));
+
+        let stream = host.build_output_stream(device_id, config, data_callback, error_callback);
+
+        // Si no se puede medir, la latencia queda como desconocida (0) en lugar de abortar
+        match stream {
+            Ok(stream) if stream.play().is_ok() => receiver
+                .recv_timeout(LATENCY_PROBE_TIMEOUT)
+                .map(|median_millis| median_millis / 1000.0)
+                .unwrap_or(0.0),
+            Ok(_) => 0.0,
+            Err(err) => {
+                eprintln!("No se pudo crear el stream de salida temporal: {}", err);
+                0.0
+            }
+        }
     }
 
     fn calculate_median_latency(latencies: &[f32]) -> f32 {
@@ -120,9 +208,253 @@ impl AudioDestinationNode {
         }
     }
 
+    // Cambia el dispositivo de salida, reconstruyendo el stream y volviendo a medir la latencia. El stream actual
+    // solo se pausa mientras se abre el nuevo (para que el grafo no se renderice desde dos callbacks a la vez); si la
+    // apertura falla se reanuda y el destino sigue en el dispositivo anterior. El grafo ya está funcionando a la tasa
+    // actual, así que se rechazan los dispositivos que no la soportan.
+    pub fn switch_device(&mut self, device_manager: &DeviceManager, device_id: &str) -> Result<(), Box<dyn Error>> {
+        let device = device_manager.find_output_device(device_id)?;
+        if !device.supports_sample_rate(self.config.sample_rate) {
+            return Err(format!(
+                "NotSupportedError: El dispositivo '{}' no soporta la tasa de muestreo del contexto ({} Hz)",
+                device_id, self.config.sample_rate
+            )
+            .into());
+        }
+
+        let _ = self.stream.pause();
+
+        let host = device_manager.host();
+        let opened = match Self::open_device(
+            host.as_ref(),
+            &device,
+            self.config.sample_rate,
+            &self.graph,
+            &self.event_sender,
+        ) {
+            Ok(opened) => opened,
+            Err(err) => {
+                self.stream.play()?;
+                return Err(err);
+            }
+        };
+
+        self.stream = opened.stream;
+        self.device_id = opened.device_id;
+        self.config = opened.config;
+        self.output_latency = opened.output_latency;
+        self.graph
+            .lock()
+            .unwrap()
+            .set_channel_count(self.config.channels as usize);
+
+        let _ = self.event_sender.send(DeviceEvent::DeviceSwitched {
+            id: self.device_id.clone(),
+        });
+        Ok(())
+    }
+
+    pub fn device_id(&self) -> &str {
+        &self.device_id
+    }
+
     pub fn output_latency(&self) -> f32 {
         self.output_latency
     }
+
+    // Devuelve los eventos de dispositivo pendientes sin bloquear
+    pub fn poll_events(&self) -> Vec<DeviceEvent> {
+        self.events.try_iter().collect()
+    }
+}
+
+impl AudioDestinationNode for RealtimeDestination {
+    fn sample_rate(&self) -> f32 {
+        self.config.sample_rate as f32
+    }
+
+    fn channel_count(&self) -> u16 {
+        self.config.channels
+    }
+}
+
+// Destino offline: acumula lo renderizado en un buffer en memoria, sin tocar cpal
+pub struct OfflineDestination {
+    sample_rate: f32,
+    channels: Vec<Vec<f32>>,
+    frames_written: usize,
+}
+
+impl OfflineDestination {
+    pub fn new(number_of_channels: u32, length: usize, sample_rate: f32) -> Self {
+        Self {
+            sample_rate,
+            channels: vec![vec![0.0; length]; number_of_channels as usize],
+            frames_written: 0,
+        }
+    }
+
+    pub fn length(&self) -> usize {
+        self.channels.first().map(Vec::len).unwrap_or(0)
+    }
+
+    pub fn frames_written(&self) -> usize {
+        self.frames_written
+    }
+
+    // Copia un bloque (un buffer por canal) al final de lo ya escrito; lo que exceda la longitud del buffer se descarta
+    pub fn write(&mut self, block: &[Vec<f32>]) {
+        let frames = block
+            .first()
+            .map_or(0, Vec::len)
+            .min(self.length() - self.frames_written);
+        let range = self.frames_written..self.frames_written + frames;
+
+        for (channel, data) in self.channels.iter_mut().zip(block) {
+            channel[range.clone()].copy_from_slice(&data[..frames]);
+        }
+        self.frames_written += frames;
+    }
+
+    pub fn into_buffer(self) -> Result<AudioBuffer, Box<dyn Error>> {
+        AudioBuffer::from_channel_data(self.channels, self.sample_rate)
+    }
+}
+
+impl AudioDestinationNode for OfflineDestination {
+    fn sample_rate(&self) -> f32 {
+        self.sample_rate
+    }
+
+    fn channel_count(&self) -> u16 {
+        self.channels.len() as u16
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio_api::mock_host::{MockFailure, MockHost};
+
+    const STEREO_48K: OutputConfig = OutputConfig {
+        channels: 2,
+        sample_rate: 48000,
+    };
+
+    fn graph() -> Arc<Mutex<AudioGraph>> {
+        Arc::new(Mutex::new(AudioGraph::new(2)))
+    }
+
+    #[test]
+    fn falls_back_to_default_when_requested_device_fails_to_open() {
+        for failure in [MockFailure::Build, MockFailure::Play] {
+            let host = Arc::new(
+                MockHost::new()
+                    .with_device("default", true, STEREO_48K)
+                    .with_failing_device("usb", false, STEREO_48K, failure),
+            );
+            let manager = DeviceManager::with_host(host.clone());
+
+            let destination = RealtimeDestination::new(&manager, Some("usb"), 48000.0, graph()).unwrap();
+
+            assert_eq!(destination.device_id(), "default");
+            assert_eq!(host.playing_devices(), ["default"]);
+            assert!((destination.output_latency() - 0.01).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn falls_back_to_default_when_requested_device_is_missing() {
+        let host = Arc::new(MockHost::new().with_device("default", true, STEREO_48K));
+        let manager = DeviceManager::with_host(host.clone());
+
+        let destination = RealtimeDestination::new(&manager, Some("missing"), 44100.0, graph()).unwrap();
+
+        assert_eq!(destination.device_id(), "default");
+        // El dispositivo no soporta 44,1 kHz, así que se queda con su propia tasa
+        assert_eq!(destination.sample_rate(), 48000.0);
+    }
+
+    #[test]
+    fn switch_device_moves_stream_to_new_device() {
+        let host = Arc::new(
+            MockHost::new()
+                .with_device("default", true, STEREO_48K)
+                .with_device("usb", false, STEREO_48K),
+        );
+        let manager = DeviceManager::with_host(host.clone());
+        let mut destination = RealtimeDestination::new(&manager, None, 48000.0, graph()).unwrap();
+
+        destination.switch_device(&manager, "usb").unwrap();
+
+        assert_eq!(destination.device_id(), "usb");
+        assert_eq!(host.playing_devices(), ["usb"]);
+        assert_eq!(
+            destination.poll_events(),
+            [DeviceEvent::DeviceSwitched { id: "usb".to_string() }]
+        );
+    }
+
+    #[test]
+    fn failed_switch_keeps_current_device_and_stream() {
+        let host = Arc::new(
+            MockHost::new()
+                .with_device("default", true, STEREO_48K)
+                .with_failing_device("usb", false, STEREO_48K, MockFailure::Build),
+        );
+        let manager = DeviceManager::with_host(host.clone());
+        let mut destination = RealtimeDestination::new(&manager, None, 48000.0, graph()).unwrap();
+
+        assert!(destination.switch_device(&manager, "usb").is_err());
+        assert!(destination.switch_device(&manager, "missing").is_err());
+
+        assert_eq!(destination.device_id(), "default");
+        assert_eq!(host.playing_devices(), ["default"]);
+        assert!(destination.poll_events().is_empty());
+    }
+
+    #[test]
+    fn unplugging_the_device_reports_device_lost() {
+        let host = Arc::new(
+            MockHost::new()
+                .with_device("default", true, STEREO_48K)
+                .with_device("usb", false, STEREO_48K),
+        );
+        let manager = DeviceManager::with_host(host.clone());
+        let destination = RealtimeDestination::new(&manager, Some("usb"), 48000.0, graph()).unwrap();
+
+        // Los streams de otros dispositivos no se ven afectados
+        host.unplug("default");
+        assert!(destination.poll_events().is_empty());
+
+        host.unplug("usb");
+        assert_eq!(
+            destination.poll_events(),
+            [DeviceEvent::DeviceLost { id: "usb".to_string() }]
+        );
+    }
+
+    #[test]
+    fn switch_device_rejects_devices_without_the_context_rate() {
+        let stereo_44k = OutputConfig {
+            channels: 2,
+            sample_rate: 44100,
+        };
+        let host = Arc::new(
+            MockHost::new()
+                .with_device("default", true, STEREO_48K)
+                .with_device("usb", false, stereo_44k),
+        );
+        let manager = DeviceManager::with_host(host.clone());
+        let mut destination = RealtimeDestination::new(&manager, None, 48000.0, graph()).unwrap();
+
+        let err = destination.switch_device(&manager, "usb").unwrap_err();
+
+        assert!(err.to_string().starts_with("NotSupportedError"));
+        assert_eq!(destination.device_id(), "default");
+        assert_eq!(destination.sample_rate(), 48000.0);
+        assert_eq!(host.playing_devices(), ["default"]);
+    }
 }
 
 // use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
@@ -0,0 +1,426 @@
+use std::error::Error;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use super::nodes::{mix_inputs, AudioNode};
+
+// Tamaño del bloque de render en frames
+pub const RENDER_QUANTUM_SIZE: usize = 128;
+
+// Identificador de un nodo dentro del grafo de un contexto
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct NodeId(usize);
+
+impl NodeId {
+    // El destino siempre ocupa la primera posición del grafo
+    pub const DESTINATION: NodeId = NodeId(0);
+}
+
+// Entrada del destino: solo suma todo lo que se le conecta
+struct DestinationInput;
+
+impl AudioNode for DestinationInput {
+    fn process(
+        &mut self,
+        inputs: &[&[Vec<f32>]],
+        output: &mut [Vec<f32>],
+        frames: usize,
+        _sample_rate: f32,
+        _current_time: f64,
+    ) {
+        mix_inputs(inputs, output, frames);
+    }
+}
+
+// Grafo de nodos que se renderiza en bloques hacia el destino. Todos los bloques tienen el número de canales del grafo.
+pub struct AudioGraph {
+    nodes: Vec<Option<Arc<Mutex<dyn AudioNode>>>>, // None para los nodos ya eliminados
+    inputs: Vec<Vec<usize>>,                       // Para cada nodo, los nodos conectados a su entrada
+    order: Vec<usize>,                             // Orden topológico de procesamiento
+    outputs: Vec<Vec<Vec<f32>>>,
+    channels: usize,
+    current_frame: u64, // Frames renderizados desde que se creó el grafo: el reloj del contexto
+    free: Vec<usize>,   // Posiciones de nodos eliminados, que se reutilizan al añadir nodos
+    visited: Vec<bool>, // Memoria de trabajo de los recorridos del grafo
+}
+
+impl AudioGraph {
+    pub fn new(channels: usize) -> Self {
+        let mut graph = Self {
+            nodes: Vec::new(),
+            inputs: Vec::new(),
+            order: Vec::new(),
+            outputs: Vec::new(),
+            channels: channels.max(1),
+            current_frame: 0,
+            free: Vec::new(),
+            visited: Vec::new(),
+        };
+        graph.add_node(Arc::new(Mutex::new(DestinationInput)));
+        graph
+    }
+
+    pub fn channel_count(&self) -> usize {
+        self.channels
+    }
+
+    // Cambia los canales de todos los bloques a partir del siguiente render (p. ej. al cambiar de dispositivo)
+    pub fn set_channel_count(&mut self, channels: usize) {
+        self.channels = channels.max(1);
+    }
+
+    // Ocupa el hueco de un nodo eliminado si lo hay, para que el grafo no crezca con cada fuente de un solo uso
+    pub fn add_node(&mut self, node: Arc<Mutex<dyn AudioNode>>) -> NodeId {
+        let id = match self.free.pop() {
+            Some(id) => {
+                self.nodes[id] = Some(node);
+                id
+            }
+            None => {
+                self.nodes.push(Some(node));
+                self.inputs.push(Vec::new());
+                self.outputs.push(Vec::new());
+                self.nodes.len() - 1
+            }
+        };
+        self.update_order();
+        NodeId(id)
+    }
+
+    // Quita el nodo del grafo junto con todas sus conexiones; deja de procesarse desde el siguiente render
+    pub fn remove_node(&mut self, id: NodeId) {
+        if id == NodeId::DESTINATION || !self.contains(id) {
+            return;
+        }
+
+        self.disconnect(id);
+        self.nodes[id.0] = None;
+        self.inputs[id.0].clear();
+        self.outputs[id.0] = Vec::new();
+        self.free.push(id.0);
+        self.update_order();
+    }
+
+    fn contains(&self, id: NodeId) -> bool {
+        matches!(self.nodes.get(id.0), Some(Some(_)))
+    }
+
+    pub fn connect(&mut self, source: NodeId, destination: NodeId) -> Result<(), Box<dyn Error>> {
+        if !self.contains(source) || !self.contains(destination) {
+            return Err("InvalidAccessError: El nodo no pertenece a este contexto".into());
+        }
+        if source == NodeId::DESTINATION {
+            return Err("IndexSizeError: El destino no tiene salidas".into());
+        }
+        if source == destination || self.is_upstream(destination.0, source.0) {
+            return Err("InvalidAccessError: La conexión crearía un ciclo en el grafo".into());
+        }
+
+        if !self.inputs[destination.0].contains(&source.0) {
+            self.inputs[destination.0].push(source.0);
+            self.update_order();
+        }
+        Ok(())
+    }
+
+    // Elimina todas las conexiones salientes del nodo
+    pub fn disconnect(&mut self, source: NodeId) {
+        for inputs in self.inputs.iter_mut() {
+            inputs.retain(|&input| input != source.0);
+        }
+        self.update_order();
+    }
+
+    // Marcas de visitado a false para todos los nodos, reutilizando la memoria del recorrido anterior
+    fn take_visited(&mut self) -> Vec<bool> {
+        let mut visited = std::mem::take(&mut self.visited);
+        visited.clear();
+        visited.resize(self.nodes.len(), false);
+        visited
+    }
+
+    // Comprueba si `candidate` alimenta (directa o indirectamente) a `node`
+    fn is_upstream(&mut self, candidate: usize, node: usize) -> bool {
+        let mut visited = self.take_visited();
+        let mut pending = vec![node];
+        let mut found = false;
+
+        while let Some(current) = pending.pop() {
+            if current == candidate {
+                found = true;
+                break;
+            }
+            if !std::mem::replace(&mut visited[current], true) {
+                pending.extend(self.inputs[current].iter().copied());
+            }
+        }
+
+        self.visited = visited;
+        found
+    }
+
+    // Orden topológico por DFS: cada nodo aparece después de todos sus entradas
+    fn update_order(&mut self) {
+        fn visit(node: usize, inputs: &[Vec<usize>], visited: &mut [bool], order: &mut Vec<usize>) {
+            if std::mem::replace(&mut visited[node], true) {
+                return;
+            }
+            for &input in &inputs[node] {
+                visit(input, inputs, visited, order);
+            }
+            order.push(node);
+        }
+
+        let mut visited = self.take_visited();
+        let mut order = std::mem::take(&mut self.order);
+        order.clear();
+        for node in 0..self.nodes.len() {
+            if self.nodes[node].is_some() {
+                visit(node, &self.inputs, &mut visited, &mut order);
+            }
+        }
+        self.order = order;
+        self.visited = visited;
+    }
+
+    // Procesa un bloque de `frames` frames y devuelve lo que llega al destino, un buffer por canal
+    pub fn render(&mut self, frames: usize, sample_rate: f32) -> &[Vec<f32>] {
+        let current_time = self.current_frame as f64 / sample_rate as f64;
+
+        for index in 0..self.order.len() {
+            let node = self.order[index];
+
+            let mut output = std::mem::take(&mut self.outputs[node]);
+            output.resize_with(self.channels, Vec::new);
+            for channel in output.iter_mut() {
+                channel.clear();
+                channel.resize(frames, 0.0);
+            }
+
+            if let Some(processor) = &self.nodes[node] {
+                let inputs: Vec<&[Vec<f32>]> = self.inputs[node]
+                    .iter()
+                    .map(|&input| self.outputs[input].as_slice())
+                    .collect();
+                let mut processor = processor.lock().unwrap();
+                processor.process(&inputs, &mut output, frames, sample_rate, current_time);
+            }
+
+            self.outputs[node] = output;
+        }
+        self.current_frame += frames as u64;
+
+        &self.outputs[NodeId::DESTINATION.0]
+    }
+}
+
+// Referencia a un nodo registrado en el grafo de un contexto
+pub struct AudioNodeHandle<T: AudioNode> {
+    id: NodeId,
+    node: Arc<Mutex<T>>,
+    graph: Arc<Mutex<AudioGraph>>,
+}
+
+impl<T: AudioNode + 'static> AudioNodeHandle<T> {
+    pub(crate) fn register(graph: &Arc<Mutex<AudioGraph>>, node: T) -> Self {
+        let node = Arc::new(Mutex::new(node));
+        let id = graph.lock().unwrap().add_node(node.clone());
+
+        Self {
+            id,
+            node,
+            graph: Arc::clone(graph),
+        }
+    }
+
+    pub fn id(&self) -> NodeId {
+        self.id
+    }
+
+    // Acceso al nodo para cambiar sus parámetros
+    pub fn node(&self) -> MutexGuard<'_, T> {
+        self.node.lock().unwrap()
+    }
+
+    pub fn connect<U: AudioNode>(&self, destination: &AudioNodeHandle<U>) -> Result<(), Box<dyn Error>> {
+        if !Arc::ptr_eq(&self.graph, &destination.graph) {
+            return Err("InvalidAccessError: El nodo de destino pertenece a otro contexto".into());
+        }
+        self.graph.lock().unwrap().connect(self.id, destination.id)
+    }
+
+    pub fn connect_to_destination(&self) -> Result<(), Box<dyn Error>> {
+        self.graph.lock().unwrap().connect(self.id, NodeId::DESTINATION)
+    }
+
+    pub fn disconnect(&self) {
+        self.graph.lock().unwrap().disconnect(self.id);
+    }
+}
+
+// Al soltar la referencia el nodo sale del grafo, así que no se sigue procesando
+impl<T: AudioNode> Drop for AudioNodeHandle<T> {
+    fn drop(&mut self) {
+        if let Ok(mut graph) = self.graph.lock() {
+            graph.remove_node(self.id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio_api::{AudioBuffer, AudioBufferSourceNode, GainNode, OscillatorNode};
+
+    const SAMPLE_RATE: f32 = 48000.0;
+
+    fn new_graph(channels: usize) -> Arc<Mutex<AudioGraph>> {
+        Arc::new(Mutex::new(AudioGraph::new(channels)))
+    }
+
+    // Renderiza `frames` frames (múltiplo del bloque de render) y los devuelve separados por canal
+    fn render(graph: &Arc<Mutex<AudioGraph>>, frames: usize) -> Vec<Vec<f32>> {
+        let mut graph = graph.lock().unwrap();
+        let mut output: Vec<Vec<f32>> = (0..graph.channel_count()).map(|_| Vec::with_capacity(frames)).collect();
+        for _ in 0..frames / RENDER_QUANTUM_SIZE {
+            let block = graph.render(RENDER_QUANTUM_SIZE, SAMPLE_RATE);
+            for (channel, data) in output.iter_mut().zip(block) {
+                channel.extend_from_slice(data);
+            }
+        }
+        output
+    }
+
+    #[test]
+    fn oscillator_through_gain_reaches_every_channel() {
+        let graph = new_graph(2);
+        let oscillator = AudioNodeHandle::register(&graph, OscillatorNode::new());
+        let gain = AudioNodeHandle::register(&graph, GainNode::new());
+        gain.node().set_gain(0.5);
+        oscillator.connect(&gain).unwrap();
+        gain.connect_to_destination().unwrap();
+
+        let output = render(&graph, 48000);
+
+        // 440 Hz durante un segundo: 440 cruces por cero ascendentes (el primero puede caer justo en la muestra 0)
+        let crossings = output[0]
+            .windows(2)
+            .filter(|pair| pair[0] < 0.0 && pair[1] >= 0.0)
+            .count();
+        assert!((439..=440).contains(&crossings), "{} cruces", crossings);
+
+        // Pasados 100 ms el suavizado de la ganancia ya ha convergido
+        let peak = output[0][4800..]
+            .iter()
+            .fold(0.0f32, |peak, sample| peak.max(sample.abs()));
+        assert!((peak - 0.5).abs() < 1e-3, "pico {}", peak);
+        assert_eq!(output[0], output[1]);
+    }
+
+    #[test]
+    fn buffer_source_keeps_channels_separate() {
+        let buffer = AudioBuffer::from_channel_data(vec![vec![0.25; 1024], vec![-0.75; 1024]], SAMPLE_RATE).unwrap();
+        let buffer = Arc::new(Mutex::new(buffer));
+
+        for (channels, expected) in [(2, vec![0.25, -0.75]), (1, vec![-0.25])] {
+            let graph = new_graph(channels);
+            let source = AudioNodeHandle::register(&graph, AudioBufferSourceNode::new());
+            source.node().set_buffer(Arc::clone(&buffer)).unwrap();
+            source.node().start(None, None, None).unwrap();
+            source.connect_to_destination().unwrap();
+
+            let output = render(&graph, 512);
+
+            for (channel, value) in output.iter().zip(expected) {
+                assert!(channel.iter().all(|&sample| (sample - value).abs() < 1e-6));
+            }
+        }
+    }
+
+    #[test]
+    fn buffer_source_plays_when_buffer_is_set_after_rendering() {
+        let graph = new_graph(1);
+        let source = AudioNodeHandle::register(&graph, AudioBufferSourceNode::new());
+        source.connect_to_destination().unwrap();
+
+        // El callback en tiempo real procesa el nodo antes de que tenga buffer
+        assert!(render(&graph, RENDER_QUANTUM_SIZE)[0]
+            .iter()
+            .all(|&sample| sample == 0.0));
+
+        let buffer = AudioBuffer::from_channel_data(vec![vec![0.5; 1024]], SAMPLE_RATE).unwrap();
+        source.node().set_buffer(Arc::new(Mutex::new(buffer))).unwrap();
+        source.node().start(None, None, None).unwrap();
+
+        let output = render(&graph, 512);
+        assert!(output[0].iter().all(|&sample| (sample - 0.5).abs() < 1e-6));
+    }
+
+    #[test]
+    fn connect_rejects_nodes_from_another_graph() {
+        let graph = new_graph(2);
+        let other_graph = new_graph(2);
+        let oscillator = AudioNodeHandle::register(&graph, OscillatorNode::new());
+        let gain = AudioNodeHandle::register(&other_graph, GainNode::new());
+
+        let err = oscillator.connect(&gain).unwrap_err();
+        assert!(err.to_string().starts_with("InvalidAccessError"));
+    }
+
+    #[test]
+    fn connect_rejects_cycles() {
+        let graph = new_graph(1);
+        let first = AudioNodeHandle::register(&graph, GainNode::new());
+        let second = AudioNodeHandle::register(&graph, GainNode::new());
+        let third = AudioNodeHandle::register(&graph, GainNode::new());
+
+        let err = first.connect(&first).unwrap_err();
+        assert!(err.to_string().starts_with("InvalidAccessError"));
+
+        first.connect(&second).unwrap();
+        let err = second.connect(&first).unwrap_err();
+        assert!(err.to_string().starts_with("InvalidAccessError"));
+
+        // También a través de nodos intermedios
+        second.connect(&third).unwrap();
+        let err = third.connect(&first).unwrap_err();
+        assert!(err.to_string().starts_with("InvalidAccessError"));
+    }
+
+    #[test]
+    fn dropping_a_handle_removes_the_node() {
+        let graph = new_graph(1);
+        let oscillator = AudioNodeHandle::register(&graph, OscillatorNode::new());
+        let gain = AudioNodeHandle::register(&graph, GainNode::new());
+        oscillator.connect(&gain).unwrap();
+        gain.connect_to_destination().unwrap();
+
+        drop(gain);
+
+        assert_eq!(graph.lock().unwrap().order.len(), 2);
+        assert!(render(&graph, 256)[0].iter().all(|&sample| sample == 0.0));
+        assert!(oscillator.connect_to_destination().is_ok());
+        assert!(render(&graph, 256)[0].iter().any(|&sample| sample != 0.0));
+    }
+
+    #[test]
+    fn removed_nodes_free_their_slot() {
+        let graph = new_graph(1);
+        let gain = AudioNodeHandle::register(&graph, GainNode::new());
+        gain.connect_to_destination().unwrap();
+
+        // Una fuente de un solo uso por sonido no hace crecer el grafo
+        for _ in 0..100 {
+            let source = AudioNodeHandle::register(&graph, AudioBufferSourceNode::new());
+            source.connect(&gain).unwrap();
+            render(&graph, RENDER_QUANTUM_SIZE);
+        }
+        assert_eq!(graph.lock().unwrap().nodes.len(), 3);
+
+        // El hueco reutilizado empieza sin conexiones y funciona como cualquier otro nodo
+        let oscillator = AudioNodeHandle::register(&graph, OscillatorNode::new());
+        assert!(graph.lock().unwrap().inputs[oscillator.id().0].is_empty());
+        assert!(render(&graph, 256)[0].iter().all(|&sample| sample == 0.0));
+        oscillator.connect(&gain).unwrap();
+        assert!(render(&graph, 256)[0].iter().any(|&sample| sample != 0.0));
+    }
+}
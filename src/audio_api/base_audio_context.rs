@@ -0,0 +1,52 @@
+use std::sync::{Arc, Mutex};
+
+use super::audio_graph::{AudioGraph, AudioNodeHandle};
+use super::nodes::{AudioBufferSourceNode, GainNode, OscillatorNode};
+use super::{AudioBuffer, AudioBufferOptions};
+
+// Funcionalidad compartida por AudioContext y OfflineAudioContext: ambos crean nodos sobre su propio grafo
+pub trait BaseAudioContext {
+    fn sample_rate(&self) -> f32;
+
+    fn graph(&self) -> &Arc<Mutex<AudioGraph>>;
+
+    fn create_gain(&self) -> AudioNodeHandle<GainNode> {
+        AudioNodeHandle::register(self.graph(), GainNode::new())
+    }
+
+    fn create_oscillator(&self) -> AudioNodeHandle<OscillatorNode> {
+        AudioNodeHandle::register(self.graph(), OscillatorNode::new())
+    }
+
+    fn create_buffer_source(&self) -> AudioNodeHandle<AudioBufferSourceNode> {
+        AudioNodeHandle::register(self.graph(), AudioBufferSourceNode::new())
+    }
+
+    fn create_buffer(&self, number_of_channels: u32, length: u32, sample_rate: f32) -> Arc<Mutex<AudioBuffer>> {
+        let options = AudioBufferOptions {
+            number_of_channels,
+            length,
+            sample_rate,
+        };
+        Arc::new(Mutex::new(AudioBuffer::new(options).expect("Error creating buffer")))
+    }
+
+    fn create_buffer_from_data(&self, data: Vec<Vec<f32>>, sample_rate: f32) -> Arc<Mutex<AudioBuffer>> {
+        let number_of_channels = data.len() as u32;
+        let length = data[0].len() as u32;
+        let options = AudioBufferOptions {
+            number_of_channels,
+            length,
+            sample_rate,
+        };
+        let mut buffer = AudioBuffer::new(options).expect("Error creating buffer");
+
+        for (channel, channel_data) in data.iter().enumerate() {
+            buffer
+                .copy_to_channel(channel_data, channel as u32, 0)
+                .expect("Error copying data to buffer");
+        }
+
+        Arc::new(Mutex::new(buffer))
+    }
+}
@@ -0,0 +1,290 @@
+use std::error::Error;
+use std::sync::Arc;
+use std::time::Duration;
+
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use cpal::{FromSample, SampleFormat, SizedSample};
+
+// Información pública de un dispositivo de salida
+#[derive(Clone, Debug, PartialEq)]
+pub struct OutputDeviceInfo {
+    pub id: String,
+    pub name: String,
+    pub is_default: bool,
+    pub sample_rates: Vec<(u32, u32)>, // Rangos (mínimo, máximo) soportados
+    pub channels: Vec<u16>,
+}
+
+impl OutputDeviceInfo {
+    pub fn supports_sample_rate(&self, sample_rate: u32) -> bool {
+        self.sample_rates
+            .iter()
+            .any(|&(min, max)| sample_rate >= min && sample_rate <= max)
+    }
+}
+
+// Configuración con la que se abre un stream de salida
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct OutputConfig {
+    pub channels: u16,
+    pub sample_rate: u32,
+}
+
+// Eventos emitidos por el nodo de destino sobre el estado del dispositivo
+#[derive(Clone, Debug, PartialEq)]
+pub enum DeviceEvent {
+    DeviceLost { id: String },
+    DeviceSwitched { id: String },
+}
+
+// Errores que puede reportar un stream de salida ya abierto
+#[derive(Clone, Debug, PartialEq)]
+pub enum StreamFailure {
+    DeviceNotAvailable,
+    Other(String),
+}
+
+// Recibe el buffer intercalado a rellenar y, si el backend la conoce, la latencia hasta la reproducción
+pub type OutputCallback = Box<dyn FnMut(&mut [f32], Option<Duration>) + Send + 'static>;
+pub type ErrorCallback = Box<dyn FnMut(StreamFailure) + Send + 'static>;
+
+// Stream de salida abierto sobre un dispositivo
+pub trait OutputStream {
+    fn play(&self) -> Result<(), Box<dyn Error>>;
+    fn pause(&self) -> Result<(), Box<dyn Error>>;
+}
+
+// Abstracción mínima sobre el host de audio (cpal en producción)
+pub trait AudioHost: Send + Sync {
+    fn output_devices(&self) -> Result<Vec<OutputDeviceInfo>, Box<dyn Error>>;
+
+    fn default_output_config(&self, device_id: &str) -> Result<OutputConfig, Box<dyn Error>>;
+
+    fn build_output_stream(
+        &self,
+        device_id: &str,
+        config: OutputConfig,
+        data_callback: OutputCallback,
+        error_callback: ErrorCallback,
+    ) -> Result<Box<dyn OutputStream>, Box<dyn Error>>;
+}
+
+// Host real basado en cpal. Como cpal no expone identificadores estables, se usa el nombre del dispositivo.
+pub struct CpalHost {
+    host: cpal::Host,
+}
+
+impl CpalHost {
+    pub fn new() -> Self {
+        Self {
+            host: cpal::default_host(),
+        }
+    }
+
+    fn find_device(&self, device_id: &str) -> Result<cpal::Device, Box<dyn Error>> {
+        self.host
+            .output_devices()?
+            .find(|device| device.name().map(|name| name == device_id).unwrap_or(false))
+            .ok_or_else(|| format!("NotFoundError: No existe el dispositivo de salida '{}'", device_id).into())
+    }
+
+    fn build_typed_stream<T>(
+        device: &cpal::Device,
+        config: &cpal::StreamConfig,
+        mut data_callback: OutputCallback,
+        mut error_callback: ErrorCallback,
+    ) -> Result<cpal::Stream, Box<dyn Error>>
+    where
+        T: SizedSample + FromSample<f32>,
+    {
+        let mut scratch: Vec<f32> = Vec::new();
+
+        let stream = device.build_output_stream(
+            config,
+            move |data: &mut [T], info: &cpal::OutputCallbackInfo| {
+                let timestamp = info.timestamp();
+                let latency = timestamp.playback.duration_since(&timestamp.callback);
+
+                scratch.clear();
+                scratch.resize(data.len(), 0.0);
+                data_callback(&mut scratch, latency);
+
+                for (out, &sample) in data.iter_mut().zip(scratch.iter()) {
+                    *out = T::from_sample(sample);
+                }
+            },
+            move |err| {
+                let failure = match err {
+                    cpal::StreamError::DeviceNotAvailable => StreamFailure::DeviceNotAvailable,
+                    other => StreamFailure::Other(other.to_string()),
+                };
+                error_callback(failure);
+            },
+            None,
+        )?;
+
+        Ok(stream)
+    }
+}
+
+impl AudioHost for CpalHost {
+    fn output_devices(&self) -> Result<Vec<OutputDeviceInfo>, Box<dyn Error>> {
+        let default_name = self.host.default_output_device().and_then(|device| device.name().ok());
+
+        let mut devices = Vec::new();
+        for device in self.host.output_devices()? {
+            // Los dispositivos sin nombre no pueden seleccionarse después, así que se omiten
+            let Ok(name) = device.name() else { continue };
+
+            let mut sample_rates = Vec::new();
+            let mut channels = Vec::new();
+            if let Ok(configs) = device.supported_output_configs() {
+                for range in configs {
+                    sample_rates.push((range.min_sample_rate().0, range.max_sample_rate().0));
+                    if !channels.contains(&range.channels()) {
+                        channels.push(range.channels());
+                    }
+                }
+            }
+            sample_rates.sort_unstable();
+            sample_rates.dedup();
+            channels.sort_unstable();
+
+            devices.push(OutputDeviceInfo {
+                id: name.clone(),
+                is_default: default_name.as_deref() == Some(name.as_str()),
+                name,
+                sample_rates,
+                channels,
+            });
+        }
+
+        Ok(devices)
+    }
+
+    fn default_output_config(&self, device_id: &str) -> Result<OutputConfig, Box<dyn Error>> {
+        let config = self.find_device(device_id)?.default_output_config()?;
+        Ok(OutputConfig {
+            channels: config.channels(),
+            sample_rate: config.sample_rate().0,
+        })
+    }
+
+    fn build_output_stream(
+        &self,
+        device_id: &str,
+        config: OutputConfig,
+        data_callback: OutputCallback,
+        error_callback: ErrorCallback,
+    ) -> Result<Box<dyn OutputStream>, Box<dyn Error>> {
+        let device = self.find_device(device_id)?;
+        let sample_format = device.default_output_config()?.sample_format();
+
+        let stream_config = cpal::StreamConfig {
+            channels: config.channels,
+            sample_rate: cpal::SampleRate(config.sample_rate),
+            buffer_size: cpal::BufferSize::Default,
+        };
+
+        let stream = match sample_format {
+            SampleFormat::F32 => {
+                Self::build_typed_stream::<f32>(&device, &stream_config, data_callback, error_callback)?
+            }
+            SampleFormat::I16 => {
+                Self::build_typed_stream::<i16>(&device, &stream_config, data_callback, error_callback)?
+            }
+            SampleFormat::U16 => {
+                Self::build_typed_stream::<u16>(&device, &stream_config, data_callback, error_callback)?
+            }
+            other => return Err(format!("NotSupportedError: Formato de muestra no soportado: {:?}", other).into()),
+        };
+
+        Ok(Box::new(CpalOutputStream { stream }))
+    }
+}
+
+struct CpalOutputStream {
+    stream: cpal::Stream,
+}
+
+impl OutputStream for CpalOutputStream {
+    fn play(&self) -> Result<(), Box<dyn Error>> {
+        self.stream.play()?;
+        Ok(())
+    }
+
+    fn pause(&self) -> Result<(), Box<dyn Error>> {
+        self.stream.pause()?;
+        Ok(())
+    }
+}
+
+// Punto de entrada para listar dispositivos y compartir el host con los nodos de destino
+#[derive(Clone)]
+pub struct DeviceManager {
+    host: Arc<dyn AudioHost>,
+}
+
+impl DeviceManager {
+    pub fn new() -> Self {
+        Self::with_host(Arc::new(CpalHost::new()))
+    }
+
+    pub fn with_host(host: Arc<dyn AudioHost>) -> Self {
+        Self { host }
+    }
+
+    pub fn host(&self) -> Arc<dyn AudioHost> {
+        Arc::clone(&self.host)
+    }
+
+    pub fn output_devices(&self) -> Result<Vec<OutputDeviceInfo>, Box<dyn Error>> {
+        self.host.output_devices()
+    }
+
+    pub fn default_output_device(&self) -> Result<OutputDeviceInfo, Box<dyn Error>> {
+        self.output_devices()?
+            .into_iter()
+            .find(|device| device.is_default)
+            .ok_or_else(|| "NotFoundError: No se encontró un dispositivo de salida".into())
+    }
+
+    pub fn find_output_device(&self, device_id: &str) -> Result<OutputDeviceInfo, Box<dyn Error>> {
+        self.output_devices()?
+            .into_iter()
+            .find(|device| device.id == device_id)
+            .ok_or_else(|| format!("NotFoundError: No existe el dispositivo de salida '{}'", device_id).into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio_api::mock_host::MockHost;
+
+    #[test]
+    fn lists_devices_and_finds_default() {
+        let config = OutputConfig {
+            channels: 2,
+            sample_rate: 48000,
+        };
+        let host = MockHost::new()
+            .with_device("speakers", false, config)
+            .with_device("usb", true, config);
+        let manager = DeviceManager::with_host(Arc::new(host));
+
+        let ids: Vec<String> = manager
+            .output_devices()
+            .unwrap()
+            .into_iter()
+            .map(|device| device.id)
+            .collect();
+        assert_eq!(ids, ["speakers", "usb"]);
+        assert_eq!(manager.default_output_device().unwrap().id, "usb");
+
+        let usb = manager.find_output_device("usb").unwrap();
+        assert!(usb.supports_sample_rate(48000));
+        assert!(!usb.supports_sample_rate(44100));
+        assert!(manager.find_output_device("missing").is_err());
+    }
+}
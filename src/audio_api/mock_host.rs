@@ -0,0 +1,195 @@
+use std::error::Error;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::Duration;
+
+use super::device_manager::{
+    AudioHost, ErrorCallback, OutputCallback, OutputConfig, OutputDeviceInfo, OutputStream, StreamFailure,
+};
+
+// Callbacks que se simulan al arrancar cada stream (los mismos que necesita la medición de latencia)
+const MOCK_CALLBACKS: usize = 20;
+const MOCK_BUFFER_FRAMES: usize = 256;
+const MOCK_LATENCY: Duration = Duration::from_millis(10);
+
+// Punto en el que falla la apertura de un dispositivo simulado
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MockFailure {
+    Build,
+    Play,
+}
+
+struct MockDevice {
+    info: OutputDeviceInfo,
+    config: OutputConfig,
+    failure: Option<MockFailure>,
+}
+
+// Host de audio para tests: dispositivos definidos a mano y fallos de apertura programados
+#[derive(Default)]
+pub struct MockHost {
+    devices: Vec<MockDevice>,
+    playing: Arc<Mutex<Vec<String>>>,
+    streams: Mutex<Vec<Weak<MockStream>>>, // Streams creados que siguen vivos
+}
+
+impl MockHost {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_device(mut self, id: &str, is_default: bool, config: OutputConfig) -> Self {
+        self.devices.push(MockDevice {
+            info: OutputDeviceInfo {
+                id: id.to_string(),
+                name: format!("Mock {}", id),
+                is_default,
+                sample_rates: vec![(config.sample_rate, config.sample_rate)],
+                channels: vec![config.channels],
+            },
+            config,
+            failure: None,
+        });
+        self
+    }
+
+    pub fn with_failing_device(self, id: &str, is_default: bool, config: OutputConfig, failure: MockFailure) -> Self {
+        let mut host = self.with_device(id, is_default, config);
+        if let Some(device) = host.devices.last_mut() {
+            device.failure = Some(failure);
+        }
+        host
+    }
+
+    // Dispositivos con un stream reproduciéndose en este momento
+    pub fn playing_devices(&self) -> Vec<String> {
+        self.playing.lock().unwrap().clone()
+    }
+
+    // Ejecuta `callbacks` callbacks de datos en los streams del dispositivo que se están reproduciendo, como si el
+    // backend pidiera más audio
+    pub fn run_callbacks(&self, device_id: &str, callbacks: usize) {
+        for stream in self.streams(device_id) {
+            if stream.playing.load(Ordering::SeqCst) {
+                stream.run_callbacks(callbacks);
+            }
+        }
+    }
+
+    // Simula la desconexión del dispositivo: sus streams reportan DeviceNotAvailable
+    pub fn unplug(&self, device_id: &str) {
+        for stream in self.streams(device_id) {
+            let mut error_callback = stream.error_callback.lock().unwrap();
+            error_callback(StreamFailure::DeviceNotAvailable);
+        }
+    }
+
+    fn streams(&self, device_id: &str) -> Vec<Arc<MockStream>> {
+        let mut streams = self.streams.lock().unwrap();
+        streams.retain(|stream| stream.strong_count() > 0);
+        streams
+            .iter()
+            .filter_map(Weak::upgrade)
+            .filter(|stream| stream.device_id == device_id)
+            .collect()
+    }
+
+    fn device(&self, device_id: &str) -> Result<&MockDevice, Box<dyn Error>> {
+        self.devices
+            .iter()
+            .find(|device| device.info.id == device_id)
+            .ok_or_else(|| format!("NotFoundError: No existe el dispositivo de salida '{}'", device_id).into())
+    }
+}
+
+impl AudioHost for MockHost {
+    fn output_devices(&self) -> Result<Vec<OutputDeviceInfo>, Box<dyn Error>> {
+        Ok(self.devices.iter().map(|device| device.info.clone()).collect())
+    }
+
+    fn default_output_config(&self, device_id: &str) -> Result<OutputConfig, Box<dyn Error>> {
+        Ok(self.device(device_id)?.config)
+    }
+
+    fn build_output_stream(
+        &self,
+        device_id: &str,
+        config: OutputConfig,
+        data_callback: OutputCallback,
+        error_callback: ErrorCallback,
+    ) -> Result<Box<dyn OutputStream>, Box<dyn Error>> {
+        let device = self.device(device_id)?;
+        if device.failure == Some(MockFailure::Build) {
+            return Err(format!("No se pudo crear el stream de '{}'", device_id).into());
+        }
+
+        let stream = Arc::new(MockStream {
+            device_id: device_id.to_string(),
+            channels: config.channels.max(1) as usize,
+            fail_on_play: device.failure == Some(MockFailure::Play),
+            data_callback: Mutex::new(data_callback),
+            error_callback: Mutex::new(error_callback),
+            playing: AtomicBool::new(false),
+            host_playing: Arc::clone(&self.playing),
+        });
+        self.streams.lock().unwrap().push(Arc::downgrade(&stream));
+        Ok(Box::new(MockOutputStream(stream)))
+    }
+}
+
+struct MockStream {
+    device_id: String,
+    channels: usize,
+    fail_on_play: bool,
+    data_callback: Mutex<OutputCallback>,
+    error_callback: Mutex<ErrorCallback>,
+    playing: AtomicBool,
+    host_playing: Arc<Mutex<Vec<String>>>,
+}
+
+impl MockStream {
+    fn run_callbacks(&self, callbacks: usize) {
+        let mut buffer = vec![0.0; MOCK_BUFFER_FRAMES * self.channels];
+        let mut guard = self.data_callback.lock().unwrap();
+        let data_callback: &mut OutputCallback = &mut guard;
+        for _ in 0..callbacks {
+            data_callback(&mut buffer, Some(MOCK_LATENCY));
+        }
+    }
+}
+
+// El host solo guarda una referencia débil, así que el stream se cierra al soltar esta caja
+struct MockOutputStream(Arc<MockStream>);
+
+impl OutputStream for MockOutputStream {
+    // Ejecuta de inmediato unos cuantos callbacks, como haría el backend al arrancar
+    fn play(&self) -> Result<(), Box<dyn Error>> {
+        let stream = &self.0;
+        if stream.fail_on_play {
+            return Err(format!("No se pudo iniciar el stream de '{}'", stream.device_id).into());
+        }
+        if !stream.playing.swap(true, Ordering::SeqCst) {
+            stream.host_playing.lock().unwrap().push(stream.device_id.clone());
+        }
+
+        stream.run_callbacks(MOCK_CALLBACKS);
+        Ok(())
+    }
+
+    fn pause(&self) -> Result<(), Box<dyn Error>> {
+        let stream = &self.0;
+        if stream.playing.swap(false, Ordering::SeqCst) {
+            let mut playing = stream.host_playing.lock().unwrap();
+            if let Some(index) = playing.iter().position(|id| *id == stream.device_id) {
+                playing.remove(index);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Drop for MockOutputStream {
+    fn drop(&mut self) {
+        let _ = self.pause();
+    }
+}
@@ -1,7 +1,28 @@
 mod audio_buffer;
 mod audio_context;
+mod audio_decoder;
 mod audio_destination_node;
+mod audio_graph;
+mod base_audio_context;
+mod device_manager;
+#[cfg(test)]
+pub(crate) mod mock_host;
 mod nodes;
+mod offline_audio_context;
+mod resampler;
+#[cfg(test)]
+pub(crate) mod test_wav;
 
 pub use audio_buffer::{AudioBuffer, AudioBufferOptions};
-pub use audio_context::AudioContext;
+pub use audio_context::{AudioContext, AudioContextLatencyCategory, AudioContextState};
+pub use audio_decoder::{DecodeError, PcmStream};
+pub use audio_destination_node::{AudioDestinationNode, OfflineDestination, RealtimeDestination};
+pub use audio_graph::{AudioGraph, AudioNodeHandle, NodeId, RENDER_QUANTUM_SIZE};
+pub use base_audio_context::BaseAudioContext;
+pub use device_manager::{
+    AudioHost, CpalHost, DeviceEvent, DeviceManager, OutputCallback, OutputConfig, OutputDeviceInfo, OutputStream,
+    StreamFailure,
+};
+pub use nodes::{remix, AudioBufferSourceNode, AudioNode, GainNode, OscillatorNode, OscillatorType};
+pub use offline_audio_context::{OfflineAudioContext, OfflineRenderResult};
+pub use resampler::{ResampleQuality, ResamplerCache, StreamResampler};
@@ -1,7 +1,7 @@
 use std::error::Error;
 use std::sync::{Arc, Mutex};
 
-use crate::audio_api::audio_destination_node::AudioDestinationNode;
+use super::{remix, AudioNode};
 use crate::audio_api::AudioBuffer;
 
 #[derive(Clone)]
@@ -24,8 +24,10 @@ pub struct AudioBufferSourceNode {
     started: bool,
     entered_loop: bool,
     buffer_time_elapsed: f64,
-    // Tasa de muestreo del contexto (suponemos 44100 Hz)
+    // Tasa de muestreo del contexto, se actualiza en cada bloque procesado
     sample_rate: f64,
+    // Posición en el buffer de cada frame del bloque actual (None para silencio)
+    positions: Vec<Option<f64>>,
 }
 
 impl AudioBufferSourceNode {
@@ -47,6 +49,7 @@ impl AudioBufferSourceNode {
             entered_loop: false,
             buffer_time_elapsed: 0.0,
             sample_rate: 44100.0,
+            positions: Vec::with_capacity(128),
         }
     }
 
@@ -102,7 +105,7 @@ impl AudioBufferSourceNode {
     }
 
     // Función para obtener la señal de reproducción en una posición dada
-    fn playback_signal(&self, channel_data: &[f32], position: f64) -> f32 {
+    fn playback_signal(channel_data: &[f32], buffer_sample_rate: f64, position: f64) -> f32 {
         /*
             Esta función proporciona la señal de reproducción para el buffer,
             mapeando desde una posición de cabezal de reproducción a un valor de
@@ -110,7 +113,7 @@ impl AudioBufferSourceNode {
             ese valor; de lo contrario, realiza una interpolación lineal entre las
             muestras vecinas.
         */
-        let sample_pos = position * self.sample_rate;
+        let sample_pos = position * buffer_sample_rate;
         let index = sample_pos.floor() as usize;
         let alpha = sample_pos - index as f64;
 
@@ -125,9 +128,51 @@ impl AudioBufferSourceNode {
         }
     }
 
-    // Función de procesamiento que genera un bloque de audio
-    pub fn process(&mut self, number_of_frames: usize, current_time: f64) -> Vec<f32> {
-        let mut output = Vec::with_capacity(number_of_frames);
+    // Escribe en cada canal de salida la señal del buffer en las posiciones calculadas para el bloque
+    fn write_output(&self, output: &mut [Vec<f32>]) {
+        let frames = self.positions.len();
+        let Some(buffer) = &self.buffer else {
+            // Buffer nulo, salida en silencio
+            for channel in output.iter_mut() {
+                channel[..frames].fill(0.0);
+            }
+            return;
+        };
+
+        let buffer = buffer.lock().unwrap();
+        let input_channels = buffer.number_of_channels() as usize;
+        let output_channels = output.len();
+        let buffer_sample_rate = buffer.sample_rate() as f64;
+        let buffer_duration = buffer.duration();
+
+        for (frame, &position) in self.positions.iter().enumerate() {
+            for (channel, data) in output.iter_mut().enumerate() {
+                data[frame] = match position {
+                    Some(position) if position >= 0.0 && position < buffer_duration => {
+                        remix(input_channels, output_channels, channel, |input| {
+                            let channel_data = buffer.channel_data(input as u32).unwrap();
+                            Self::playback_signal(channel_data, buffer_sample_rate, position)
+                        })
+                    }
+                    _ => 0.0, // Silencio o fuera del rango del buffer
+                };
+            }
+        }
+    }
+}
+
+impl AudioNode for AudioBufferSourceNode {
+    // Genera un bloque de audio a partir del tiempo del contexto que entrega el grafo. Primero se calcula la posición
+    // de cada frame y después se leen todos los canales del buffer, adaptándolos a los canales del grafo.
+    fn process(
+        &mut self,
+        _inputs: &[&[Vec<f32>]],
+        output: &mut [Vec<f32>],
+        frames: usize,
+        sample_rate: f32,
+        current_time: f64,
+    ) {
+        self.sample_rate = sample_rate as f64;
 
         // Combina los parámetros playbackRate y detune
         let computed_playback_rate = self.playback_rate * 2f32.powf(self.detune / 1200.0);
@@ -149,35 +194,38 @@ impl AudioBufferSourceNode {
             (0.0, 0.0)
         };
 
-        // Maneja el caso de buffer nulo
+        let dt = 1.0 / self.sample_rate;
+        let block_start = current_time;
+
+        // Buffer nulo: silencio sin tocar start()/stop(), que siguen valiendo cuando se asigne el buffer
         if self.buffer.is_none() {
-            self.stop_time = current_time; // Fuerza salida en silencio
+            for channel in output.iter_mut() {
+                channel[..frames].fill(0.0);
+            }
+            return;
         }
 
-        let dt = 1.0 / self.sample_rate;
+        self.positions.clear();
+
+        for frame in 0..frames {
+            let current_time = block_start + frame as f64 * dt;
 
-        for _ in 0..number_of_frames {
             // Verifica si currentTime y bufferTimeElapsed están dentro del rango de reproducción
-            if current_time < self.start_time
+            if !self.is_playing
+                || current_time < self.start_time
                 || current_time >= self.stop_time
                 || self.buffer_time_elapsed >= self.duration
             {
-                output.push(0.0); // Muestra silenciosa
+                self.positions.push(None); // Muestra silenciosa
                 continue;
             }
 
             if !self.started {
                 // Nota que el buffer ha comenzado a reproducirse y obtiene la posición inicial
-                if self.loop_playback
-                    && computed_playback_rate >= 0.0
-                    && self.offset >= actual_loop_end
-                {
+                if self.loop_playback && computed_playback_rate >= 0.0 && self.offset >= actual_loop_end {
                     self.offset = actual_loop_end;
                 }
-                if computed_playback_rate < 0.0
-                    && self.loop_playback
-                    && self.offset < actual_loop_start
-                {
+                if computed_playback_rate < 0.0 && self.loop_playback && self.offset < actual_loop_start {
                     self.offset = actual_loop_start;
                 }
                 self.buffer_time = self.offset;
@@ -209,38 +257,18 @@ impl AudioBufferSourceNode {
                 }
             }
 
-            let sample_value = if let Some(buffer) = &self.buffer {
-                let mut buffer = buffer.lock().unwrap();
-                if self.buffer_time >= 0.0 && self.buffer_time < buffer.duration() {
-                    let channel_data = buffer.get_channel_data(0).unwrap();
-                    self.playback_signal(channel_data, self.buffer_time)
-                } else {
-                    0.0 // Fuera del rango del buffer, salida en silencio
-                }
-            } else {
-                0.0 // Buffer nulo, salida en silencio
-            };
-
-            output.push(sample_value);
+            self.positions.push(Some(self.buffer_time));
 
             // Actualiza las variables de tiempo
             self.buffer_time += dt * computed_playback_rate;
             self.buffer_time_elapsed += dt * computed_playback_rate;
-            // currentTime += dt; // En este contexto, currentTime se pasa externamente
         }
 
-        if current_time >= self.stop_time {
+        self.write_output(output);
+
+        if block_start + frames as f64 * dt >= self.stop_time {
             // Finaliza el estado de reproducción de este nodo
             self.is_playing = false;
         }
-
-        output
-    }
-
-    pub fn connect(&self, destination: &mut AudioDestinationNode) {
-        let source = Arc::new(Mutex::new(self.clone()));
-
-        // Añade este AudioBufferSourceNode al nodo de destino
-        // destination.add_source(source.clone());
     }
 }
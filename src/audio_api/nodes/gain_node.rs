@@ -1,21 +1,49 @@
-use super::AudioNode;
+use super::{mix_inputs, AudioNode};
+
+// Constante de tiempo del suavizado de ganancia, evita el "zipper noise" al cambiarla
+const GAIN_SMOOTHING_SECONDS: f32 = 0.005;
 
 // GainNode to control volume
 pub struct GainNode {
     gain: f32,
+    current_gain: f32,
 }
 
 impl GainNode {
     pub fn new() -> Self {
-        GainNode { gain: 1.0 }
+        GainNode {
+            gain: 1.0,
+            current_gain: 1.0,
+        }
+    }
+
+    pub fn gain(&self) -> f32 {
+        self.gain
     }
 
     pub fn set_gain(&mut self, gain: f32) {
         self.gain = gain;
     }
 }
+
 impl AudioNode for GainNode {
-    fn connect(&self, destination: &dyn AudioNode) {
-        // Connect gain node to another node
+    fn process(
+        &mut self,
+        inputs: &[&[Vec<f32>]],
+        output: &mut [Vec<f32>],
+        frames: usize,
+        sample_rate: f32,
+        _current_time: f64,
+    ) {
+        mix_inputs(inputs, output, frames);
+
+        // Filtro de un polo hacia la ganancia objetivo, común a todos los canales
+        let coefficient = 1.0 - (-1.0 / (GAIN_SMOOTHING_SECONDS * sample_rate)).exp();
+        for frame in 0..frames {
+            self.current_gain += (self.gain - self.current_gain) * coefficient;
+            for channel in output.iter_mut() {
+                channel[frame] *= self.current_gain;
+            }
+        }
     }
 }
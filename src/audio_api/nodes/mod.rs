@@ -3,10 +3,48 @@ mod oscillator_node;
 mod audio_buffer_source_node;
 
 pub use gain_node::GainNode;
-pub use oscillator_node::OscillatorNode;
+pub use oscillator_node::{OscillatorNode, OscillatorType};
 pub use audio_buffer_source_node::AudioBufferSourceNode;
 
-// A basic AudioNode trait that different node types will implement
-pub trait AudioNode {
-    fn connect(&self, destination: &dyn AudioNode);
+// A basic AudioNode trait that different node types will implement.
+// Connections live in the context's render graph; a node only has to turn its mixed inputs into one output block.
+// Every block carries one buffer per channel, and all of them use the channel count of the graph.
+// `current_time` is the context time, in seconds, of the first frame of the block.
+pub trait AudioNode: Send {
+    fn process(
+        &mut self,
+        inputs: &[&[Vec<f32>]],
+        output: &mut [Vec<f32>],
+        frames: usize,
+        sample_rate: f32,
+        current_time: f64,
+    );
+}
+
+// Sums every connected input into the output block, channel by channel
+pub fn mix_inputs(inputs: &[&[Vec<f32>]], output: &mut [Vec<f32>], frames: usize) {
+    for channel in output.iter_mut() {
+        channel[..frames].fill(0.0);
+    }
+    for input in inputs {
+        for (out, data) in output.iter_mut().zip(input.iter()) {
+            for (out, &sample) in out[..frames].iter_mut().zip(data.iter()) {
+                *out += sample;
+            }
+        }
+    }
+}
+
+// Value of output `channel` taken from a signal with a different channel count: a mono signal goes to every channel,
+// a mono output gets the average of all channels, and otherwise channels are matched by index (missing ones are silent)
+pub fn remix<F: Fn(usize) -> f32>(input_channels: usize, output_channels: usize, channel: usize, sample: F) -> f32 {
+    if input_channels == 1 {
+        sample(0)
+    } else if output_channels == 1 {
+        (0..input_channels).map(&sample).sum::<f32>() / input_channels as f32
+    } else if channel < input_channels {
+        sample(channel)
+    } else {
+        0.0
+    }
 }
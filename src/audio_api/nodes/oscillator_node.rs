@@ -1,22 +1,81 @@
+use std::f32::consts::TAU;
+
 use super::AudioNode;
 
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum OscillatorType {
+    Sine,
+    Square,
+    Sawtooth,
+    Triangle,
+}
+
 // OscillatorNode for generating waveforms
 pub struct OscillatorNode {
     frequency: f32,
+    oscillator_type: OscillatorType,
+    phase: f32, // Fase normalizada en [0, 1)
 }
 
 impl OscillatorNode {
     pub fn new() -> Self {
-        OscillatorNode { frequency: 440.0 }
+        OscillatorNode {
+            frequency: 440.0,
+            oscillator_type: OscillatorType::Sine,
+            phase: 0.0,
+        }
+    }
+
+    pub fn frequency(&self) -> f32 {
+        self.frequency
     }
 
     pub fn set_frequency(&mut self, frequency: f32) {
         self.frequency = frequency;
     }
+
+    pub fn oscillator_type(&self) -> OscillatorType {
+        self.oscillator_type
+    }
+
+    pub fn set_type(&mut self, oscillator_type: OscillatorType) {
+        self.oscillator_type = oscillator_type;
+    }
+
+    fn sample_at(&self, phase: f32) -> f32 {
+        match self.oscillator_type {
+            OscillatorType::Sine => (phase * TAU).sin(),
+            OscillatorType::Square => {
+                if phase < 0.5 {
+                    1.0
+                } else {
+                    -1.0
+                }
+            }
+            OscillatorType::Sawtooth => 2.0 * phase - 1.0,
+            OscillatorType::Triangle => 1.0 - 4.0 * (phase - 0.5).abs(),
+        }
+    }
 }
 
 impl AudioNode for OscillatorNode {
-    fn connect(&self, destination: &dyn AudioNode) {
-        // Connect oscillator to another node
+    // La forma de onda es mono: se escribe igual en todos los canales
+    fn process(
+        &mut self,
+        _inputs: &[&[Vec<f32>]],
+        output: &mut [Vec<f32>],
+        frames: usize,
+        sample_rate: f32,
+        _current_time: f64,
+    ) {
+        let increment = self.frequency / sample_rate;
+
+        for frame in 0..frames {
+            let value = self.sample_at(self.phase);
+            for channel in output.iter_mut() {
+                channel[frame] = value;
+            }
+            self.phase = (self.phase + increment).rem_euclid(1.0);
+        }
     }
 }
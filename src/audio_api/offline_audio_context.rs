@@ -0,0 +1,217 @@
+use std::collections::BTreeSet;
+use std::error::Error;
+use std::sync::{Arc, Mutex};
+
+use super::audio_context::AudioContextState;
+use super::audio_destination_node::OfflineDestination;
+use super::audio_graph::{AudioGraph, RENDER_QUANTUM_SIZE};
+use super::{AudioBuffer, BaseAudioContext};
+
+// Resultado de avanzar el renderizado offline
+pub enum OfflineRenderResult {
+    // Se alcanzó una suspensión programada; los parámetros pueden cambiarse antes de llamar a resume()
+    Suspended { frame: usize },
+    Complete(AudioBuffer),
+}
+
+// Contexto que renderiza el grafo a un buffer, de forma síncrona y sin depender del tiempo real
+pub struct OfflineAudioContext {
+    sample_rate: f32,
+    length: usize,
+    destination: Option<OfflineDestination>,
+    graph: Arc<Mutex<AudioGraph>>,
+    suspensions: BTreeSet<usize>,
+    state: AudioContextState,
+    rendering_started: bool,
+}
+
+impl OfflineAudioContext {
+    pub fn new(number_of_channels: u32, length: usize, sample_rate: f32) -> Result<Self, Box<dyn Error>> {
+        if number_of_channels == 0 || length == 0 || sample_rate <= 0.0 {
+            return Err("NotSupportedError: Valores fuera de rango".into());
+        }
+
+        Ok(Self {
+            sample_rate,
+            length,
+            destination: Some(OfflineDestination::new(number_of_channels, length, sample_rate)),
+            graph: Arc::new(Mutex::new(AudioGraph::new(number_of_channels as usize))),
+            suspensions: BTreeSet::new(),
+            state: AudioContextState::Suspended,
+            rendering_started: false,
+        })
+    }
+
+    pub fn length(&self) -> usize {
+        self.length
+    }
+
+    pub fn state(&self) -> &AudioContextState {
+        &self.state
+    }
+
+    // Frames ya renderizados
+    pub fn current_frame(&self) -> usize {
+        self.destination
+            .as_ref()
+            .map(OfflineDestination::frames_written)
+            .unwrap_or(self.length)
+    }
+
+    pub fn current_time(&self) -> f64 {
+        self.current_frame() as f64 / self.sample_rate as f64
+    }
+
+    // Programa una suspensión. El instante se redondea hacia abajo al inicio de su bloque de render.
+    pub fn suspend(&mut self, frame: usize) -> Result<(), Box<dyn Error>> {
+        let frame = frame / RENDER_QUANTUM_SIZE * RENDER_QUANTUM_SIZE;
+
+        if frame >= self.length {
+            return Err("InvalidStateError: El instante de suspensión excede la duración del contexto".into());
+        }
+        if self.rendering_started && frame <= self.current_frame() {
+            return Err("InvalidStateError: El instante de suspensión ya ha sido renderizado".into());
+        }
+        if !self.suspensions.insert(frame) {
+            return Err("InvalidStateError: Ya existe una suspensión programada en ese instante".into());
+        }
+        Ok(())
+    }
+
+    pub fn start_rendering(&mut self) -> Result<OfflineRenderResult, Box<dyn Error>> {
+        if self.rendering_started {
+            return Err("InvalidStateError: start_rendering() solo puede llamarse una vez".into());
+        }
+        self.rendering_started = true;
+        self.render()
+    }
+
+    pub fn resume(&mut self) -> Result<OfflineRenderResult, Box<dyn Error>> {
+        if !self.rendering_started || self.state != AudioContextState::Suspended {
+            return Err("InvalidStateError: El contexto no está suspendido".into());
+        }
+        self.render()
+    }
+
+    // Renderiza bloque a bloque hasta la siguiente suspensión o hasta completar el buffer
+    fn render(&mut self) -> Result<OfflineRenderResult, Box<dyn Error>> {
+        let destination = self
+            .destination
+            .as_mut()
+            .ok_or("InvalidStateError: El renderizado ya ha terminado")?;
+        self.state = AudioContextState::Running;
+
+        while destination.frames_written() < self.length {
+            let frame = destination.frames_written();
+            if self.suspensions.remove(&frame) {
+                self.state = AudioContextState::Suspended;
+                return Ok(OfflineRenderResult::Suspended { frame });
+            }
+
+            let mut graph = self.graph.lock().unwrap();
+            destination.write(graph.render(RENDER_QUANTUM_SIZE, self.sample_rate));
+        }
+
+        self.state = AudioContextState::Closed;
+        let buffer = self.destination.take().unwrap().into_buffer()?;
+        Ok(OfflineRenderResult::Complete(buffer))
+    }
+}
+
+impl BaseAudioContext for OfflineAudioContext {
+    fn sample_rate(&self) -> f32 {
+        self.sample_rate
+    }
+
+    fn graph(&self) -> &Arc<Mutex<AudioGraph>> {
+        &self.graph
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::FRAC_1_SQRT_2;
+
+    use super::*;
+
+    fn rms(samples: &[f32]) -> f32 {
+        (samples.iter().map(|sample| sample * sample).sum::<f32>() / samples.len() as f32).sqrt()
+    }
+
+    fn complete(result: OfflineRenderResult) -> AudioBuffer {
+        match result {
+            OfflineRenderResult::Complete(buffer) => buffer,
+            OfflineRenderResult::Suspended { frame } => panic!("suspendido en el frame {}", frame),
+        }
+    }
+
+    #[test]
+    fn renders_one_second_of_sine() {
+        let mut context = OfflineAudioContext::new(1, 44100, 44100.0).unwrap();
+        let oscillator = context.create_oscillator();
+        oscillator.connect_to_destination().unwrap();
+
+        let buffer = complete(context.start_rendering().unwrap());
+
+        // 44100 no es múltiplo del bloque de render, pero el buffer tiene exactamente la longitud pedida
+        assert_eq!(buffer.length(), 44100);
+        assert_eq!(buffer.sample_rate(), 44100.0);
+        let level = rms(buffer.channel_data(0).unwrap());
+        assert!((level - FRAC_1_SQRT_2).abs() < 1e-3, "rms {}", level);
+        assert_eq!(*context.state(), AudioContextState::Closed);
+    }
+
+    #[test]
+    fn suspend_allows_changing_parameters_mid_render() {
+        let mut context = OfflineAudioContext::new(2, 44100, 44100.0).unwrap();
+        let oscillator = context.create_oscillator();
+        let gain = context.create_gain();
+        oscillator.connect(&gain).unwrap();
+        gain.connect_to_destination().unwrap();
+        context.suspend(22050).unwrap();
+
+        let frame = match context.start_rendering().unwrap() {
+            OfflineRenderResult::Suspended { frame } => frame,
+            OfflineRenderResult::Complete(_) => panic!("el renderizado no se suspendió"),
+        };
+        // La suspensión se redondea al inicio de su bloque de render
+        assert_eq!(frame, 22016);
+        assert_eq!(context.current_frame(), 22016);
+        assert_eq!(*context.state(), AudioContextState::Suspended);
+
+        gain.node().set_gain(0.25);
+        let buffer = complete(context.resume().unwrap());
+
+        for channel in 0..2 {
+            let data = buffer.channel_data(channel).unwrap();
+            let before = rms(&data[..22016]);
+            // Se deja margen para el suavizado de la ganancia (unos 5 ms)
+            let after = rms(&data[22016 + 2205..]);
+            assert!((before - FRAC_1_SQRT_2).abs() < 1e-2, "rms {}", before);
+            assert!((after - 0.25 * FRAC_1_SQRT_2).abs() < 1e-2, "rms {}", after);
+        }
+    }
+
+    #[test]
+    fn start_time_follows_the_context_clock() {
+        let mut context = OfflineAudioContext::new(1, 44100, 44100.0).unwrap();
+        context.suspend(22050).unwrap();
+        assert!(matches!(
+            context.start_rendering().unwrap(),
+            OfflineRenderResult::Suspended { frame: 22016 }
+        ));
+
+        // Una fuente creada a mitad del renderizado programa start() en tiempo del contexto, no desde su creación
+        let source = context.create_buffer_source();
+        let buffer = context.create_buffer_from_data(vec![vec![0.5; 44100]], 44100.0);
+        source.node().set_buffer(buffer).unwrap();
+        source.node().start(Some(0.6), None, None).unwrap();
+        source.connect_to_destination().unwrap();
+
+        let buffer = complete(context.resume().unwrap());
+
+        let data = buffer.channel_data(0).unwrap();
+        assert!(data[..26459].iter().all(|&sample| sample == 0.0));
+        assert!(data[26461..].iter().all(|&sample| (sample - 0.5).abs() < 1e-6));
+    }
+}
@@ -0,0 +1,373 @@
+use std::collections::HashMap;
+use std::error::Error;
+
+use rubato::{Resampler, SincFixedIn, SincInterpolationParameters, SincInterpolationType, WindowFunction};
+
+// Frames de entrada que consume el resampler en cada bloque (SincFixedIn exige un tamaño fijo)
+const RESAMPLE_CHUNK_FRAMES: usize = 1024;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ResampleQuality {
+    Fast,     // Filtro sinc corto, adecuado para previsualizaciones y conversión en tiempo real
+    Balanced, // Compromiso entre calidad y coste
+    High,     // Filtro largo con sobremuestreo alto, para conversiones offline
+}
+
+impl ResampleQuality {
+    fn parameters(self) -> SincInterpolationParameters {
+        match self {
+            ResampleQuality::Fast => SincInterpolationParameters {
+                sinc_len: 32,
+                f_cutoff: 0.85,
+                interpolation: SincInterpolationType::Linear,
+                oversampling_factor: 32,
+                window: WindowFunction::Hann2,
+            },
+            ResampleQuality::Balanced => SincInterpolationParameters {
+                sinc_len: 128,
+                f_cutoff: 0.925,
+                interpolation: SincInterpolationType::Linear,
+                oversampling_factor: 128,
+                window: WindowFunction::Blackman2,
+            },
+            ResampleQuality::High => SincInterpolationParameters {
+                sinc_len: 256,                                // Longitud del filtro sinc para una calidad alta
+                f_cutoff: 0.95,                               // Frecuencia de corte
+                interpolation: SincInterpolationType::Linear, // Interpolación lineal
+                oversampling_factor: 256,                     // Factor de sobremuestreo
+                window: WindowFunction::BlackmanHarris2,      // Ventana de Blackman-Harris
+            },
+        }
+    }
+}
+
+// Resampler con estado: convierte audio intercalado por bloques de cualquier tamaño manteniendo el filtro entre
+// llamadas. El resultado es idéntico al de convertir toda la señal de una vez.
+pub struct StreamResampler {
+    resampler: SincFixedIn<f32>,
+    ratio: f64,
+    output_sample_rate: f32,
+    channels: usize,
+    pending: Vec<Vec<f32>>,  // Entrada aún no procesada (menos de un bloque)
+    partial_frame: Vec<f32>, // Muestras de un frame incompleto al final del último bloque intercalado
+    delay_remaining: usize,  // Frames iniciales de retardo del filtro que faltan por descartar
+    frames_in: usize,
+    frames_out: usize,
+}
+
+impl StreamResampler {
+    pub fn new(
+        input_sample_rate: f32,
+        output_sample_rate: f32,
+        channels: usize,
+        quality: ResampleQuality,
+    ) -> Result<Self, Box<dyn Error>> {
+        if input_sample_rate <= 0.0 || output_sample_rate <= 0.0 || channels == 0 {
+            return Err("NotSupportedError: Valores fuera de rango".into());
+        }
+
+        let ratio = output_sample_rate as f64 / input_sample_rate as f64;
+        let resampler = SincFixedIn::<f32>::new(ratio, 1.0, quality.parameters(), RESAMPLE_CHUNK_FRAMES, channels)?;
+        let delay_remaining = resampler.output_delay();
+
+        Ok(Self {
+            resampler,
+            ratio,
+            output_sample_rate,
+            channels,
+            pending: (0..channels)
+                .map(|_| Vec::with_capacity(RESAMPLE_CHUNK_FRAMES))
+                .collect(),
+            partial_frame: Vec::with_capacity(channels),
+            delay_remaining,
+            frames_in: 0,
+            frames_out: 0,
+        })
+    }
+
+    pub fn channels(&self) -> usize {
+        self.channels
+    }
+
+    pub fn output_sample_rate(&self) -> f32 {
+        self.output_sample_rate
+    }
+
+    // Frames de salida que corresponden a `frames` frames de entrada
+    pub fn output_frames(&self, frames: usize) -> usize {
+        (frames as f64 * self.ratio).round() as usize
+    }
+
+    // Convierte un bloque intercalado y devuelve, también intercalado, todo lo que ya puede producirse. El bloque no
+    // tiene por qué contener frames completos: las muestras sobrantes se guardan hasta la siguiente llamada.
+    pub fn resample_stream(&mut self, chunk: &[f32]) -> Result<Vec<f32>, Box<dyn Error>> {
+        let mut chunk = chunk;
+        if !self.partial_frame.is_empty() {
+            let missing = (self.channels - self.partial_frame.len()).min(chunk.len());
+            self.partial_frame.extend_from_slice(&chunk[..missing]);
+            chunk = &chunk[missing..];
+
+            if self.partial_frame.len() == self.channels {
+                self.frames_in += push_frames(&mut self.pending, &self.partial_frame);
+                self.partial_frame.clear();
+            }
+        }
+
+        let complete = chunk.len() - chunk.len() % self.channels;
+        self.frames_in += push_frames(&mut self.pending, &chunk[..complete]);
+        self.partial_frame.extend_from_slice(&chunk[complete..]);
+
+        Ok(interleave(&self.process_pending()?))
+    }
+
+    // Igual que `resample_stream`, pero con la entrada y la salida separadas por canales. No debe mezclarse con
+    // bloques intercalados a medias.
+    pub fn resample_planar<V: AsRef<[f32]>>(&mut self, chunk: &[V]) -> Result<Vec<Vec<f32>>, Box<dyn Error>> {
+        if chunk.len() != self.channels {
+            return Err("IndexSizeError: El bloque no tiene el número de canales del resampler".into());
+        }
+        let frames = chunk[0].as_ref().len();
+        if chunk.iter().any(|channel| channel.as_ref().len() != frames) {
+            return Err("IndexSizeError: Los canales del bloque tienen longitudes distintas".into());
+        }
+        if !self.partial_frame.is_empty() {
+            return Err("InvalidStateError: Hay un frame intercalado incompleto pendiente".into());
+        }
+
+        for (pending, channel) in self.pending.iter_mut().zip(chunk) {
+            pending.extend_from_slice(channel.as_ref());
+        }
+        self.frames_in += frames;
+
+        self.process_pending()
+    }
+
+    // Procesa todos los bloques completos de la entrada pendiente
+    fn process_pending(&mut self) -> Result<Vec<Vec<f32>>, Box<dyn Error>> {
+        let mut output = vec![Vec::new(); self.channels];
+        while self.pending[0].len() >= RESAMPLE_CHUNK_FRAMES {
+            let block: Vec<Vec<f32>> = self
+                .pending
+                .iter_mut()
+                .map(|channel| channel.drain(..RESAMPLE_CHUNK_FRAMES).collect())
+                .collect();
+            let processed = self.resampler.process(&block, None)?;
+            self.append_output(&mut output, processed, usize::MAX);
+        }
+        Ok(output)
+    }
+
+    // Procesa la entrada pendiente (rellenando con ceros el último bloque parcial) y vacía el retardo del filtro,
+    // recortando la salida a exactamente round(frames_in * ratio) frames. Un frame incompleto al final de la entrada se
+    // descarta. Después el resampler queda reiniciado.
+    pub fn flush(&mut self) -> Result<Vec<f32>, Box<dyn Error>> {
+        Ok(interleave(&self.flush_planar()?))
+    }
+
+    // Igual que `flush`, con la salida separada por canales
+    pub fn flush_planar(&mut self) -> Result<Vec<Vec<f32>>, Box<dyn Error>> {
+        let expected = self.output_frames(self.frames_in);
+        let mut output = vec![Vec::new(); self.channels];
+
+        if !self.pending[0].is_empty() {
+            let pending = std::mem::replace(&mut self.pending, vec![Vec::new(); self.channels]);
+            let processed = self.resampler.process_partial(Some(&pending), None)?;
+            self.append_output(&mut output, processed, expected);
+        }
+        while self.frames_out < expected {
+            let processed = self.resampler.process_partial::<Vec<f32>>(None, None)?;
+            self.append_output(&mut output, processed, expected);
+        }
+
+        self.reset();
+        Ok(output)
+    }
+
+    pub fn reset(&mut self) {
+        self.resampler.reset();
+        for channel in self.pending.iter_mut() {
+            channel.clear();
+        }
+        self.partial_frame.clear();
+        self.delay_remaining = self.resampler.output_delay();
+        self.frames_in = 0;
+        self.frames_out = 0;
+    }
+
+    // Añade la salida descartando el retardo inicial y sin superar `limit` frames en total
+    fn append_output(&mut self, output: &mut [Vec<f32>], processed: Vec<Vec<f32>>, limit: usize) {
+        let available = processed[0].len();
+        let skip = self.delay_remaining.min(available);
+        self.delay_remaining -= skip;
+
+        let take = (available - skip).min(limit.saturating_sub(self.frames_out));
+        for (channel, data) in output.iter_mut().zip(processed.iter()) {
+            channel.extend_from_slice(&data[skip..skip + take]);
+        }
+        self.frames_out += take;
+    }
+}
+
+// Separa por canales frames intercalados completos y devuelve cuántos frames se añadieron
+fn push_frames(channels: &mut [Vec<f32>], samples: &[f32]) -> usize {
+    for frame in samples.chunks_exact(channels.len()) {
+        for (channel, &sample) in channels.iter_mut().zip(frame) {
+            channel.push(sample);
+        }
+    }
+    samples.len() / channels.len()
+}
+
+fn interleave(channels: &[Vec<f32>]) -> Vec<f32> {
+    let frames = channels[0].len();
+    let mut interleaved = Vec::with_capacity(frames * channels.len());
+    for frame in 0..frames {
+        interleaved.extend(channels.iter().map(|channel| channel[frame]));
+    }
+    interleaved
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+struct ResamplerKey {
+    input_sample_rate: u32,
+    output_sample_rate: u32,
+    channels: usize,
+    quality: ResampleQuality,
+}
+
+// Caché de resamplers ya construidos, para no recalcular las tablas del filtro en cada conversión
+#[derive(Default)]
+pub struct ResamplerCache {
+    resamplers: HashMap<ResamplerKey, StreamResampler>,
+    hits: u64,
+    misses: u64,
+}
+
+impl ResamplerCache {
+    // Entrega un resampler reiniciado para la conversión pedida; debe devolverse con `release` tras usarlo
+    pub fn acquire(
+        &mut self,
+        input_sample_rate: f32,
+        output_sample_rate: f32,
+        channels: usize,
+        quality: ResampleQuality,
+    ) -> Result<StreamResampler, Box<dyn Error>> {
+        let key = ResamplerKey {
+            input_sample_rate: input_sample_rate.round() as u32,
+            output_sample_rate: output_sample_rate.round() as u32,
+            channels,
+            quality,
+        };
+
+        match self.resamplers.remove(&key) {
+            Some(resampler) => {
+                self.hits += 1;
+                Ok(resampler)
+            }
+            None => {
+                self.misses += 1;
+                StreamResampler::new(input_sample_rate, output_sample_rate, channels, quality)
+            }
+        }
+    }
+
+    pub fn release(
+        &mut self,
+        input_sample_rate: f32,
+        output_sample_rate: f32,
+        quality: ResampleQuality,
+        mut resampler: StreamResampler,
+    ) {
+        resampler.reset();
+        let key = ResamplerKey {
+            input_sample_rate: input_sample_rate.round() as u32,
+            output_sample_rate: output_sample_rate.round() as u32,
+            channels: resampler.channels(),
+            quality,
+        };
+        self.resamplers.insert(key, resampler);
+    }
+
+    pub fn hits(&self) -> u64 {
+        self.hits
+    }
+
+    pub fn misses(&self) -> u64 {
+        self.misses
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Seno intercalado con la misma señal en todos los canales
+    fn sine(frequency: f32, sample_rate: f32, frames: usize, channels: usize) -> Vec<f32> {
+        (0..frames)
+            .flat_map(|frame| {
+                let value = (2.0 * std::f32::consts::PI * frequency * frame as f32 / sample_rate).sin();
+                std::iter::repeat(value).take(channels)
+            })
+            .collect()
+    }
+
+    fn resample_in_chunks(resampler: &mut StreamResampler, input: &[f32], chunk_size: usize) -> Vec<f32> {
+        let mut output = Vec::new();
+        for chunk in input.chunks(chunk_size) {
+            output.extend(resampler.resample_stream(chunk).unwrap());
+        }
+        output.extend(resampler.flush().unwrap());
+        output
+    }
+
+    #[test]
+    fn output_length_matches_ratio_for_every_quality() {
+        for quality in [ResampleQuality::Fast, ResampleQuality::Balanced, ResampleQuality::High] {
+            for (input_rate, output_rate, expected) in [(44100.0, 48000.0, 10449), (48000.0, 44100.0, 8820)] {
+                let mut resampler = StreamResampler::new(input_rate, output_rate, 2, quality).unwrap();
+                let input = sine(440.0, input_rate, 9600, 2);
+
+                let output = resample_in_chunks(&mut resampler, &input, input.len());
+
+                assert_eq!(
+                    output.len(),
+                    expected * 2,
+                    "{:?} {} -> {}",
+                    quality,
+                    input_rate,
+                    output_rate
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn chunked_conversion_matches_one_shot() {
+        let input = sine(440.0, 44100.0, 20000, 2);
+
+        let mut resampler = StreamResampler::new(44100.0, 48000.0, 2, ResampleQuality::Balanced).unwrap();
+        let one_shot = resample_in_chunks(&mut resampler, &input, input.len());
+        // Bloques de tamaño impar: la mayoría terminan a mitad de un frame
+        let chunked = resample_in_chunks(&mut resampler, &input, 777);
+
+        assert_eq!(chunked.len(), one_shot.len());
+        for (a, b) in chunked.iter().zip(one_shot.iter()) {
+            assert!((a - b).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn misaligned_chunks_keep_channels_in_place() {
+        // Canal izquierdo a 0,5 y derecho a -0,5: si se desalinearan, los canales se mezclarían
+        let input: Vec<f32> = (0..8192).map(|index| if index % 2 == 0 { 0.5 } else { -0.5 }).collect();
+        let mut resampler = StreamResampler::new(48000.0, 48000.0, 2, ResampleQuality::Fast).unwrap();
+
+        let output = resample_in_chunks(&mut resampler, &input, 3);
+
+        assert_eq!(output.len(), input.len());
+        // Se ignoran los bordes, donde el filtro ve ceros fuera de la señal
+        for frame in output.chunks_exact(2).skip(64).take(3000) {
+            assert!((frame[0] - 0.5).abs() < 1e-2 && (frame[1] + 0.5).abs() < 1e-2);
+        }
+    }
+}
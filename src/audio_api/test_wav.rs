@@ -0,0 +1,24 @@
+use std::path::PathBuf;
+
+use hound::{SampleFormat, WavSpec, WavWriter};
+
+// Escribe en el directorio temporal un WAV de 16 bits con un seno de amplitud 0,5 en todos los canales
+pub fn write_sine_wav(name: &str, sample_rate: u32, channels: u16, frames: usize, frequency: f32) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("cismu-{}-{}.wav", std::process::id(), name));
+    let spec = WavSpec {
+        channels,
+        sample_rate,
+        bits_per_sample: 16,
+        sample_format: SampleFormat::Int,
+    };
+
+    let mut writer = WavWriter::create(&path, spec).unwrap();
+    for frame in 0..frames {
+        let value = 0.5 * (2.0 * std::f32::consts::PI * frequency * frame as f32 / sample_rate as f32).sin();
+        for _ in 0..channels {
+            writer.write_sample((value * i16::MAX as f32) as i16).unwrap();
+        }
+    }
+    writer.finalize().unwrap();
+    path
+}
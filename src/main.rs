@@ -1,10 +1,10 @@
 mod application;
 mod audio_api;
+mod player;
 mod renderer;
 
 use crate::application::Application;
-use audio_api::{AudioBuffer, AudioContext};
-use hound::WavReader;
+use audio_api::{AudioBuffer, AudioContext, BaseAudioContext};
 use rand::Rng;
 use std::sync::{Arc, Mutex};
 use winit::event_loop::{ControlFlow, EventLoop};
@@ -12,7 +12,7 @@ use winit::event_loop::{ControlFlow, EventLoop};
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Inicializa el loop de eventos y el contexto de audio
     let event_loop = EventLoop::new()?;
-    let audio_context = AudioContext::new(None, None);
+    let audio_context = AudioContext::new(None, None)?;
 
     // Crea y rellena un buffer de ruido
     let buffer = initialize_audio_buffer(&audio_context)?;
@@ -21,8 +21,8 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Resamplea el buffer si es necesario
     resample_buffer_if_needed(&buffer, &audio_context)?;
 
-    // Carga y muestra la información de un archivo WAV
-    let wav_buffer = load_wav_to_buffer("windows_background.wav", &audio_context)?;
+    // Decodifica y muestra la información de un archivo WAV (ya resampleado a la tasa del contexto)
+    let wav_buffer = audio_context.decode_audio_file("windows_background.wav", None)?;
 
     display_buffer_info(&wav_buffer);
 
@@ -114,40 +114,3 @@ fn display_buffer_info_raw(buffer: &mut AudioBuffer) {
     }
     println!("...");
 }
-
-fn load_wav_to_buffer(
-    file_path: &str,
-    audio_context: &AudioContext,
-) -> Result<Arc<Mutex<AudioBuffer>>, Box<dyn std::error::Error>> {
-    let mut reader = WavReader::open(file_path)?;
-    let spec = reader.spec();
-
-    // Verificación de formato
-    if spec.sample_format != hound::SampleFormat::Float && spec.bits_per_sample != 16 {
-        return Err("Formato no soportado: se espera PCM de 16 bits o de punto flotante".into());
-    }
-
-    // Cargar datos y crear el buffer
-    let number_of_channels = spec.channels as u32;
-    let sample_rate = spec.sample_rate as f32;
-    let samples: Vec<f32> = reader
-        .samples::<i16>()
-        .map(|s| s.unwrap() as f32 / i16::MAX as f32)
-        .collect();
-
-    let length = samples.len() as u32 / number_of_channels;
-    let internal_data = organize_samples_into_channels(samples, number_of_channels, length);
-    let wav_buffer = audio_context.create_buffer_from_data(internal_data, sample_rate);
-
-    Ok(wav_buffer)
-}
-
-fn organize_samples_into_channels(samples: Vec<f32>, channels: u32, length: u32) -> Vec<Vec<f32>> {
-    let mut channel_data = vec![vec![0.0; length as usize]; channels as usize];
-    for (i, sample) in samples.iter().enumerate() {
-        let channel = i % channels as usize;
-        let frame = i / channels as usize;
-        channel_data[channel][frame] = *sample;
-    }
-    channel_data
-}
@@ -0,0 +1,719 @@
+use std::collections::VecDeque;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::audio_api::{
+    remix, AudioNode, AudioNodeHandle, BaseAudioContext, DecodeError, GainNode, PcmStream, ResampleQuality,
+    StreamResampler,
+};
+
+// Frames decodificados por bloque
+const DECODE_CHUNK_FRAMES: usize = 4096;
+// Capacidad de cada buffer circular en frames (unos 0,75 s a 44,1 kHz)
+const RING_CAPACITY_FRAMES: usize = DECODE_CHUNK_FRAMES * 8;
+// Intervalo entre eventos de posición, medido en audio reproducido
+const POSITION_INTERVAL: Duration = Duration::from_millis(250);
+// Espera máxima del hilo decodificador cuando no tiene nada que hacer
+const DECODER_IDLE_TIMEOUT: Duration = Duration::from_millis(50);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PlayerState {
+    Stopped,
+    Playing,
+    Paused,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum PlayerEvent {
+    StateChanged(PlayerState),
+    PositionChanged(Duration),
+    TrackEnded,
+    Error(String),
+}
+
+// Dónde se decodifica el audio
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DecodeMode {
+    Background, // En un hilo propio del reproductor (contextos en tiempo real)
+    Inline,     // Dentro del render, antes de cada bloque: resultado determinista con OfflineAudioContext
+}
+
+// Difunde cada evento a todos los suscriptores, olvidando los que ya se cerraron
+#[derive(Clone, Default)]
+struct EventBus {
+    subscribers: Arc<Mutex<Vec<Sender<PlayerEvent>>>>,
+}
+
+impl EventBus {
+    fn subscribe(&self) -> Receiver<PlayerEvent> {
+        let (sender, receiver) = mpsc::channel();
+        self.subscribers.lock().unwrap().push(sender);
+        receiver
+    }
+
+    fn emit(&self, event: PlayerEvent) {
+        self.subscribers
+            .lock()
+            .unwrap()
+            .retain(|subscriber| subscriber.send(event.clone()).is_ok());
+    }
+}
+
+// Audio ya decodificado de una pista, con sus propios canales y a la tasa del contexto
+struct TrackBuffer {
+    path: PathBuf,
+    ring: VecDeque<f32>, // Frames intercalados
+    channels: usize,
+    decoder_done: bool,
+}
+
+impl TrackBuffer {
+    fn new(path: PathBuf, channels: usize) -> Self {
+        Self {
+            path,
+            ring: VecDeque::with_capacity(RING_CAPACITY_FRAMES * channels),
+            channels,
+            decoder_done: false,
+        }
+    }
+
+    fn is_full(&self) -> bool {
+        self.ring.len() >= RING_CAPACITY_FRAMES * self.channels
+    }
+
+    fn push(&mut self, chunk: &[Vec<f32>]) {
+        for frame in 0..chunk[0].len() {
+            self.ring.extend(chunk.iter().map(|channel| channel[frame]));
+        }
+    }
+}
+
+// Estado compartido entre el nodo de salida, el decodificador y la API del reproductor. El reloj es el propio render:
+// la posición, el fin de pista y el paso a la pista precargada se deciden según los frames que consume el nodo.
+struct PlaybackState {
+    player_state: PlayerState,
+    current: Option<TrackBuffer>,
+    next: Option<TrackBuffer>, // Pista precargada que sigue sin pausa a la actual
+    position_frames: u64,
+    frames_since_position: u64,
+    handoffs: u64, // Cambios a la pista precargada hechos por el nodo
+}
+
+impl PlaybackState {
+    fn set_state(&mut self, new_state: PlayerState, events: &EventBus) {
+        if self.player_state != new_state {
+            self.player_state = new_state;
+            events.emit(PlayerEvent::StateChanged(new_state));
+        }
+    }
+}
+
+// Decodificador de una pista: lee el archivo por bloques y, si hace falta, lo convierte a la tasa del contexto
+struct TrackDecoder {
+    stream: PcmStream,
+    resampler: Option<StreamResampler>,
+    channels: usize,
+}
+
+impl TrackDecoder {
+    // Abre la pista en `start` y devuelve también el frame de salida que corresponde a esa posición
+    fn open(path: &Path, output_rate: f32, start: Duration) -> Result<(Self, u64), DecodeError> {
+        let mut stream = PcmStream::open(path)?;
+        let file_rate = stream.sample_rate();
+        let channels = stream.number_of_channels();
+
+        let frame = ((start.as_secs_f64() * file_rate as f64) as usize).min(stream.total_frames());
+        stream.seek(frame)?;
+
+        let resampler = if file_rate != output_rate {
+            let resampler = StreamResampler::new(file_rate, output_rate, channels, ResampleQuality::Balanced)
+                .map_err(|err| DecodeError::Resample(err.to_string()))?;
+            Some(resampler)
+        } else {
+            None
+        };
+        let output_frame = (frame as f64 * output_rate as f64 / file_rate as f64).round() as u64;
+
+        Ok((
+            Self {
+                stream,
+                resampler,
+                channels,
+            },
+            output_frame,
+        ))
+    }
+
+    // Siguiente bloque separado por canales y a la tasa del contexto. None cuando ya se decodificó toda la pista.
+    fn decode_chunk(&mut self) -> Result<Option<Vec<Vec<f32>>>, Box<dyn Error>> {
+        match self.stream.read_chunk(DECODE_CHUNK_FRAMES)? {
+            Some(chunk) => match self.resampler.as_mut() {
+                Some(resampler) => Ok(Some(resampler.resample_planar(&chunk)?)),
+                None => Ok(Some(chunk)),
+            },
+            // Vacía el retardo del filtro antes de dar la pista por terminada
+            None => match self.resampler.take() {
+                Some(mut resampler) => Ok(Some(resampler.flush_planar()?)),
+                None => Ok(None),
+            },
+        }
+    }
+}
+
+#[derive(Default)]
+struct Decoders {
+    current: Option<TrackDecoder>,
+    next: Option<TrackDecoder>,
+    handoffs: u64,
+}
+
+struct PlayerShared {
+    playback: Mutex<PlaybackState>,
+    changed: Condvar,
+    // Orden de bloqueo: siempre `decoders` antes que `playback`. El nodo solo toma `playback`.
+    decoders: Mutex<Decoders>,
+    events: EventBus,
+    mode: DecodeMode,
+    output_rate: f32,
+    position_interval: u64,
+    shutdown: AtomicBool,
+}
+
+impl PlayerShared {
+    // Bloquea los decodificadores, adoptando antes el de la pista precargada si el nodo ya cambió a ella
+    fn lock_decoders(&self) -> MutexGuard<'_, Decoders> {
+        let mut decoders = self.decoders.lock().unwrap();
+        let handoffs = self.playback.lock().unwrap().handoffs;
+        while decoders.handoffs < handoffs {
+            decoders.current = decoders.next.take();
+            decoders.handoffs += 1;
+        }
+        decoders
+    }
+
+    // Decodifica un bloque de la pista en curso o, cuando esta ya terminó, de la precargada. Devuelve false si no hay
+    // nada que decodificar o el buffer de destino está lleno.
+    fn decode_step(&self) -> bool {
+        let mut guard = self.lock_decoders();
+        let decoders = &mut *guard;
+        let queued = decoders.current.is_none();
+        let Some(decoder) = decoders.current.as_mut().or(decoders.next.as_mut()) else {
+            return false;
+        };
+
+        let handoffs = {
+            let state = self.playback.lock().unwrap();
+            let target = if queued { &state.next } else { &state.current };
+            match target {
+                Some(track) if !track.is_full() => {}
+                _ => return false,
+            }
+            state.handoffs
+        };
+
+        // La decodificación se hace sin bloquear al nodo
+        let chunk = decoder.decode_chunk();
+
+        // Si entretanto el nodo pasó a la pista precargada, el bloque pertenece ya a la pista actual
+        let mut state = self.playback.lock().unwrap();
+        let target = if queued && state.handoffs == handoffs {
+            state.next.as_mut()
+        } else {
+            state.current.as_mut()
+        };
+        let Some(target) = target else {
+            return false;
+        };
+        let finished = match chunk {
+            Ok(Some(chunk)) => {
+                target.push(&chunk);
+                false
+            }
+            Ok(None) => true,
+            Err(err) => {
+                self.events.emit(PlayerEvent::Error(err.to_string()));
+                true
+            }
+        };
+
+        if finished {
+            target.decoder_done = true;
+            if queued {
+                decoders.next = None;
+            } else {
+                decoders.current = None;
+            }
+        }
+        true
+    }
+
+    fn run_decoder(&self) {
+        while !self.shutdown.load(Ordering::Acquire) {
+            if !self.decode_step() {
+                // Sin trabajo hasta que el nodo consuma audio o cambie la pista
+                let state = self.playback.lock().unwrap();
+                if !self.shutdown.load(Ordering::Acquire) {
+                    drop(self.changed.wait_timeout(state, DECODER_IDLE_TIMEOUT).unwrap());
+                }
+            }
+        }
+    }
+
+    fn position(&self, state: &PlaybackState) -> Duration {
+        Duration::from_secs_f64(state.position_frames as f64 / self.output_rate as f64)
+    }
+
+    // Fin del audio de la pista en curso: se pasa sin pausa a la precargada o se detiene la reproducción
+    fn finish_track(&self, state: &mut PlaybackState) {
+        self.events.emit(PlayerEvent::TrackEnded);
+
+        match state.next.take() {
+            Some(next) => {
+                state.current = Some(next);
+                state.position_frames = 0;
+                state.frames_since_position = 0;
+                state.handoffs += 1;
+            }
+            None => state.set_state(PlayerState::Stopped, &self.events),
+        }
+    }
+}
+
+// Nodo que consume el audio decodificado, adaptando los canales de la pista a los del grafo
+struct PlayerSourceNode {
+    shared: Arc<PlayerShared>,
+}
+
+impl AudioNode for PlayerSourceNode {
+    fn process(
+        &mut self,
+        _inputs: &[&[Vec<f32>]],
+        output: &mut [Vec<f32>],
+        frames: usize,
+        _sample_rate: f32,
+        _current_time: f64,
+    ) {
+        if self.shared.mode == DecodeMode::Inline {
+            while self.shared.decode_step() {}
+        }
+
+        let mut guard = self.shared.playback.lock().unwrap();
+        let state = &mut *guard;
+        let output_channels = output.len();
+
+        for frame in 0..frames {
+            if state.player_state == PlayerState::Playing {
+                let ended = state
+                    .current
+                    .as_ref()
+                    .is_some_and(|track| track.ring.is_empty() && track.decoder_done);
+                if ended {
+                    self.shared.finish_track(state);
+                }
+            }
+
+            let track = match state.current.as_mut() {
+                Some(track) if state.player_state == PlayerState::Playing && !track.ring.is_empty() => track,
+                // En pausa, detenido o esperando al decodificador
+                _ => {
+                    for channel in output.iter_mut() {
+                        channel[frame] = 0.0;
+                    }
+                    continue;
+                }
+            };
+
+            for (channel, data) in output.iter_mut().enumerate() {
+                data[frame] = remix(track.channels, output_channels, channel, |input| track.ring[input]);
+            }
+            track.ring.drain(..track.channels);
+
+            state.position_frames += 1;
+            state.frames_since_position += 1;
+            if state.frames_since_position >= self.shared.position_interval {
+                state.frames_since_position = 0;
+                let position = self.shared.position(state);
+                self.shared.events.emit(PlayerEvent::PositionChanged(position));
+            }
+        }
+
+        drop(guard);
+        self.shared.changed.notify_all();
+    }
+}
+
+// Reproductor de pistas sobre el grafo de un contexto de audio: decodifica en streaming y emite eventos de estado
+pub struct PlayerEngine {
+    shared: Arc<PlayerShared>,
+    // Mantienen los nodos en el grafo mientras exista el reproductor
+    _source: AudioNodeHandle<PlayerSourceNode>,
+    gain: AudioNodeHandle<GainNode>,
+    decoder_thread: Option<JoinHandle<()>>,
+}
+
+impl PlayerEngine {
+    pub fn new(context: &impl BaseAudioContext) -> Result<Self, Box<dyn Error>> {
+        Self::with_decode_mode(context, DecodeMode::Background)
+    }
+
+    pub fn with_decode_mode(context: &impl BaseAudioContext, mode: DecodeMode) -> Result<Self, Box<dyn Error>> {
+        let output_rate = context.sample_rate();
+        let shared = Arc::new(PlayerShared {
+            playback: Mutex::new(PlaybackState {
+                player_state: PlayerState::Stopped,
+                current: None,
+                next: None,
+                position_frames: 0,
+                frames_since_position: 0,
+                handoffs: 0,
+            }),
+            changed: Condvar::new(),
+            decoders: Mutex::new(Decoders::default()),
+            events: EventBus::default(),
+            mode,
+            output_rate,
+            position_interval: ((POSITION_INTERVAL.as_secs_f64() * output_rate as f64).round() as u64).max(1),
+            shutdown: AtomicBool::new(false),
+        });
+
+        let source = AudioNodeHandle::register(
+            context.graph(),
+            PlayerSourceNode {
+                shared: Arc::clone(&shared),
+            },
+        );
+        let gain = context.create_gain();
+        source.connect(&gain)?;
+        gain.connect_to_destination()?;
+
+        let decoder_thread = match mode {
+            DecodeMode::Background => {
+                let decoder_shared = Arc::clone(&shared);
+                Some(thread::spawn(move || decoder_shared.run_decoder()))
+            }
+            DecodeMode::Inline => None,
+        };
+
+        Ok(Self {
+            shared,
+            _source: source,
+            gain,
+            decoder_thread,
+        })
+    }
+
+    pub fn subscribe(&self) -> Receiver<PlayerEvent> {
+        self.shared.events.subscribe()
+    }
+
+    pub fn state(&self) -> PlayerState {
+        self.shared.playback.lock().unwrap().player_state
+    }
+
+    pub fn position(&self) -> Duration {
+        let state = self.shared.playback.lock().unwrap();
+        self.shared.position(&state)
+    }
+
+    // Carga una pista dejándola en pausa al principio
+    pub fn load<P: AsRef<Path>>(&self, path: P) -> Result<(), DecodeError> {
+        let path = path.as_ref().to_path_buf();
+        let mut decoders = self.shared.lock_decoders();
+
+        // Reutiliza la pista precargada, con lo que ya se haya decodificado, si es la misma
+        let preloaded = {
+            let mut state = self.shared.playback.lock().unwrap();
+            match state.next.take() {
+                Some(next) if next.path == path => Some(next),
+                other => {
+                    state.next = other;
+                    None
+                }
+            }
+        };
+        let track = match preloaded {
+            Some(track) => {
+                decoders.current = decoders.next.take();
+                track
+            }
+            None => {
+                let (decoder, _) = TrackDecoder::open(&path, self.shared.output_rate, Duration::ZERO)?;
+                let track = TrackBuffer::new(path, decoder.channels);
+                decoders.current = Some(decoder);
+                track
+            }
+        };
+
+        let mut state = self.shared.playback.lock().unwrap();
+        state.current = Some(track);
+        state.position_frames = 0;
+        state.frames_since_position = 0;
+        state.set_state(PlayerState::Paused, &self.shared.events);
+        drop(state);
+        drop(decoders);
+
+        self.shared.changed.notify_all();
+        Ok(())
+    }
+
+    // Abre por adelantado la siguiente pista; su audio se decodifica al terminar el de la actual y se encadena sin pausa
+    pub fn preload_next<P: AsRef<Path>>(&self, path: P) -> Result<(), DecodeError> {
+        let path = path.as_ref().to_path_buf();
+        let (decoder, _) = TrackDecoder::open(&path, self.shared.output_rate, Duration::ZERO)?;
+
+        let mut decoders = self.shared.lock_decoders();
+        self.shared.playback.lock().unwrap().next = Some(TrackBuffer::new(path, decoder.channels));
+        decoders.next = Some(decoder);
+        drop(decoders);
+
+        self.shared.changed.notify_all();
+        Ok(())
+    }
+
+    pub fn play(&self) -> Result<(), Box<dyn Error>> {
+        let mut state = self.shared.playback.lock().unwrap();
+        let track = state
+            .current
+            .as_ref()
+            .ok_or("InvalidStateError: No hay ninguna pista cargada")?;
+        if track.decoder_done && track.ring.is_empty() {
+            return Err("InvalidStateError: La pista ya ha terminado".into());
+        }
+
+        state.set_state(PlayerState::Playing, &self.shared.events);
+        Ok(())
+    }
+
+    pub fn pause(&self) {
+        let mut state = self.shared.playback.lock().unwrap();
+        if state.player_state == PlayerState::Playing {
+            state.set_state(PlayerState::Paused, &self.shared.events);
+        }
+    }
+
+    // Reabre la pista en la posición pedida, manteniendo el estado de reproducción
+    pub fn seek(&self, position: Duration) -> Result<(), Box<dyn Error>> {
+        let mut decoders = self.shared.lock_decoders();
+        let path = self
+            .shared
+            .playback
+            .lock()
+            .unwrap()
+            .current
+            .as_ref()
+            .map(|track| track.path.clone())
+            .ok_or("InvalidStateError: No hay ninguna pista cargada")?;
+
+        let (decoder, start_frame) = TrackDecoder::open(&path, self.shared.output_rate, position)?;
+
+        let mut state = self.shared.playback.lock().unwrap();
+        state.current = Some(TrackBuffer::new(path, decoder.channels));
+        state.position_frames = start_frame;
+        state.frames_since_position = 0;
+        decoders.current = Some(decoder);
+
+        let position = self.shared.position(&state);
+        self.shared.events.emit(PlayerEvent::PositionChanged(position));
+        drop(state);
+        drop(decoders);
+
+        self.shared.changed.notify_all();
+        Ok(())
+    }
+
+    pub fn set_volume(&self, volume: f32) {
+        self.gain.node().set_gain(volume.max(0.0));
+    }
+}
+
+impl Drop for PlayerEngine {
+    fn drop(&mut self) {
+        // Se avisa con `playback` tomado para que el hilo decodificador no pierda la notificación
+        {
+            let _state = self.shared.playback.lock().unwrap();
+            self.shared.shutdown.store(true, Ordering::Release);
+            self.shared.changed.notify_all();
+        }
+
+        if let Some(decoder_thread) = self.decoder_thread.take() {
+            let _ = decoder_thread.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio_api::mock_host::MockHost;
+    use crate::audio_api::test_wav::write_sine_wav;
+    use crate::audio_api::{
+        AudioBuffer, AudioContext, DeviceManager, OfflineAudioContext, OfflineRenderResult, OutputConfig,
+    };
+
+    fn suspended(result: OfflineRenderResult) -> usize {
+        match result {
+            OfflineRenderResult::Suspended { frame } => frame,
+            OfflineRenderResult::Complete(_) => panic!("el renderizado no se suspendió"),
+        }
+    }
+
+    fn complete(result: OfflineRenderResult) -> AudioBuffer {
+        match result {
+            OfflineRenderResult::Complete(buffer) => buffer,
+            OfflineRenderResult::Suspended { frame } => panic!("suspendido en el frame {}", frame),
+        }
+    }
+
+    fn position(millis: u64) -> PlayerEvent {
+        PlayerEvent::PositionChanged(Duration::from_millis(millis))
+    }
+
+    #[test]
+    fn play_seek_pause_and_end_follow_the_render_clock() {
+        let path = write_sine_wav("player-seek", 44100, 1, 44100, 440.0);
+        let mut context = OfflineAudioContext::new(2, 66150, 44100.0).unwrap();
+        let player = PlayerEngine::with_decode_mode(&context, DecodeMode::Inline).unwrap();
+        let events = player.subscribe();
+
+        player.load(&path).unwrap();
+        player.play().unwrap();
+        context.suspend(22050).unwrap();
+        context.suspend(34816).unwrap();
+        context.suspend(47616).unwrap();
+
+        assert_eq!(suspended(context.start_rendering().unwrap()), 22016);
+        assert_eq!(player.position(), Duration::from_secs_f64(22016.0 / 44100.0));
+        player.seek(Duration::from_millis(500)).unwrap();
+        assert_eq!(player.position(), Duration::from_millis(500));
+
+        assert_eq!(suspended(context.resume().unwrap()), 34816);
+        player.pause();
+        assert_eq!(suspended(context.resume().unwrap()), 47616);
+        player.play().unwrap();
+        let buffer = complete(context.resume().unwrap());
+
+        assert_eq!(
+            events.try_iter().collect::<Vec<_>>(),
+            vec![
+                PlayerEvent::StateChanged(PlayerState::Paused),
+                PlayerEvent::StateChanged(PlayerState::Playing),
+                position(250),
+                position(500),
+                position(750),
+                PlayerEvent::StateChanged(PlayerState::Paused),
+                PlayerEvent::StateChanged(PlayerState::Playing),
+                position(1000),
+                PlayerEvent::TrackEnded,
+                PlayerEvent::StateChanged(PlayerState::Stopped),
+            ]
+        );
+        assert_eq!(player.state(), PlayerState::Stopped);
+        assert!(player.play().is_err());
+
+        // La pista mono suena igual en los dos canales; en pausa y tras el final solo hay silencio
+        let end = 47616 + (44100 - (22050 + 34816 - 22016));
+        for channel in 0..2 {
+            let data = buffer.channel_data(channel).unwrap();
+            assert_eq!(data, buffer.channel_data(0).unwrap());
+            assert!(data[34816..47616].iter().all(|&sample| sample == 0.0));
+            assert!(data[end - 1] != 0.0);
+            assert!(data[end..].iter().all(|&sample| sample == 0.0));
+        }
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn preloaded_track_follows_without_a_gap() {
+        let first = write_sine_wav("player-gapless-a", 44100, 1, 22050, 440.0);
+        let second = write_sine_wav("player-gapless-b", 22050, 2, 11025, 440.0);
+        let mut context = OfflineAudioContext::new(2, 50000, 44100.0).unwrap();
+        let player = PlayerEngine::with_decode_mode(&context, DecodeMode::Inline).unwrap();
+        let events = player.subscribe();
+
+        player.load(&first).unwrap();
+        player.preload_next(&second).unwrap();
+        player.play().unwrap();
+        let buffer = complete(context.start_rendering().unwrap());
+
+        assert_eq!(
+            events.try_iter().collect::<Vec<_>>(),
+            vec![
+                PlayerEvent::StateChanged(PlayerState::Paused),
+                PlayerEvent::StateChanged(PlayerState::Playing),
+                position(250),
+                position(500),
+                PlayerEvent::TrackEnded,
+                position(250),
+                position(500),
+                PlayerEvent::TrackEnded,
+                PlayerEvent::StateChanged(PlayerState::Stopped),
+            ]
+        );
+
+        // La segunda pista, resampleada de 22,05 kHz, empieza justo en el frame siguiente a la primera
+        for channel in 0..2 {
+            let data = buffer.channel_data(channel).unwrap();
+            assert!(!data[..44100]
+                .windows(3)
+                .any(|window| window.iter().all(|&sample| sample == 0.0)));
+            assert!(data[44100..].iter().all(|&sample| sample == 0.0));
+        }
+        std::fs::remove_file(first).unwrap();
+        std::fs::remove_file(second).unwrap();
+    }
+
+    #[test]
+    fn background_decoding_plays_to_the_end_on_a_device() {
+        let first = write_sine_wav("player-device-a", 44100, 1, 22050, 440.0);
+        let second = write_sine_wav("player-device-b", 22050, 2, 11025, 440.0);
+        let config = OutputConfig {
+            channels: 2,
+            sample_rate: 44100,
+        };
+        let host = Arc::new(MockHost::new().with_device("default", true, config));
+        let context = AudioContext::with_sink(None, None, DeviceManager::with_host(host.clone()), None).unwrap();
+        let player = PlayerEngine::new(&context).unwrap();
+        let events = player.subscribe();
+
+        player.load(&first).unwrap();
+        player.preload_next(&second).unwrap();
+        player.play().unwrap();
+
+        // El dispositivo pide audio a su ritmo mientras el hilo decodificador llena los buffers
+        let mut received = Vec::new();
+        for _ in 0..5000 {
+            host.run_callbacks("default", 1);
+            received.extend(events.try_iter());
+            if player.state() == PlayerState::Stopped {
+                break;
+            }
+            thread::sleep(Duration::from_millis(1));
+        }
+
+        // Las posiciones cuentan frames reproducidos, así que cualquier bloque perdido en el cambio de pista se notaría
+        assert_eq!(
+            received,
+            vec![
+                PlayerEvent::StateChanged(PlayerState::Paused),
+                PlayerEvent::StateChanged(PlayerState::Playing),
+                position(250),
+                position(500),
+                PlayerEvent::TrackEnded,
+                position(250),
+                position(500),
+                PlayerEvent::TrackEnded,
+                PlayerEvent::StateChanged(PlayerState::Stopped),
+            ]
+        );
+
+        // Al soltar el reproductor el hilo decodificador termina
+        drop(player);
+        std::fs::remove_file(first).unwrap();
+        std::fs::remove_file(second).unwrap();
+    }
+}